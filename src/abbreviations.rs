@@ -0,0 +1,104 @@
+/// Q-codes and CW abbreviations common on the air, with their meaning.
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("QRL", "this frequency is in use"),
+    ("QRM", "interference from other stations"),
+    ("QRN", "static noise"),
+    ("QRO", "increase power"),
+    ("QRP", "reduce power; low power"),
+    ("QRQ", "send faster"),
+    ("QRS", "send slower"),
+    ("QRT", "stop sending; closing down"),
+    ("QRU", "I have nothing for you"),
+    ("QRV", "I am ready"),
+    ("QRX", "wait; I will call you again"),
+    ("QRZ", "who is calling me?"),
+    ("QSB", "your signal is fading"),
+    ("QSL", "I acknowledge receipt"),
+    ("QSO", "a contact between stations"),
+    ("QSY", "change frequency"),
+    ("QTH", "my location is"),
+    ("QTR", "the correct time is"),
+    ("CQ", "calling any station"),
+    ("DE", "from; this is"),
+    ("73", "best regards"),
+    ("88", "love and kisses"),
+    ("TNX", "thanks"),
+    ("TKS", "thanks"),
+    ("FB", "fine business; excellent"),
+    ("RST", "readability, strength and tone report"),
+    ("UR", "your; you are"),
+    ("OM", "old man; fellow operator"),
+    ("YL", "young lady; female operator"),
+    ("OP", "operator"),
+    ("GM", "good morning"),
+    ("GA", "good afternoon"),
+    ("GE", "good evening"),
+    ("GL", "good luck"),
+    ("HW", "how do you copy?"),
+    ("PSE", "please"),
+    ("AGN", "again"),
+    ("BK", "break; back to you"),
+    ("CUL", "see you later"),
+    ("ES", "and"),
+    ("HR", "here"),
+    ("SRI", "sorry"),
+    ("RIG", "radio equipment"),
+    ("ANT", "antenna"),
+    ("WX", "weather"),
+    ("DX", "distant station"),
+];
+
+/// The meaning of a Q-code or CW abbreviation, ignoring case.
+pub fn lookup(word: &str) -> Option<&'static str> {
+    ABBREVIATIONS
+        .iter()
+        .find(|(abbreviation, _)| abbreviation.eq_ignore_ascii_case(word))
+        .map(|(_, meaning)| *meaning)
+}
+
+/// Every recognised word of `text` with its meaning, once each, in the order
+/// they first appear. A trailing `?`, which asks the Q-code as a question as
+/// in `QTH?`, is kept on the word as first seen, but `QTH?` and `QTH` are
+/// the same code.
+pub fn annotate(text: &str) -> Vec<(String, &'static str)> {
+    let mut found: Vec<(String, &'static str)> = vec![];
+    for word in text.split(|c: char| !c.is_alphanumeric() && c != '?') {
+        let code = word.trim_end_matches('?');
+        if let Some(meaning) = lookup(code) {
+            if !found.iter().any(|(w, _)| w.trim_end_matches('?').eq_ignore_ascii_case(code)) {
+                found.push((word.to_uppercase(), meaning));
+            }
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_ignores_case() {
+        assert_eq!(lookup("qth"), Some("my location is"));
+        assert_eq!(lookup("QtH"), lookup("QTH"));
+        assert_eq!(lookup("qth?"), None);
+        assert_eq!(lookup("paris"), None);
+    }
+
+    #[test]
+    fn annotate_keeps_the_question_mark() {
+        assert_eq!(annotate("qrl?"), [("QRL?".to_string(), "this frequency is in use")]);
+    }
+
+    #[test]
+    fn annotate_lists_each_code_once() {
+        let words: Vec<String> = annotate("qth? QTH es wx qth es").into_iter().map(|(w, _)| w).collect();
+        assert_eq!(words, ["QTH?", "ES", "WX"]);
+    }
+
+    #[test]
+    fn annotate_follows_first_appearance() {
+        let words: Vec<String> = annotate("cul, dx de ant... rig? cul").into_iter().map(|(w, _)| w).collect();
+        assert_eq!(words, ["CUL", "DX", "DE", "ANT", "RIG?"]);
+    }
+}
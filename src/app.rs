@@ -7,6 +7,7 @@ pub struct App {
     menu: crate::menu::Menu,
     table: crate::code_table::Table,
     decoder: crate::decoder::Decoder,
+    encoder: crate::encoder::Encoder,
 }
 
 impl Widget for &App {
@@ -20,11 +21,22 @@ impl Widget for &App {
 }
 
 impl App {
+    /// Creates an application whose views choose from the given code books.
+    pub fn with_code_books(code_books: crate::code_book::CodeBooks) -> Self {
+        Self {
+            menu: Default::default(),
+            table: crate::code_table::Table::with_code_books(code_books.clone()),
+            decoder: crate::decoder::Decoder::with_code_books(code_books.clone()),
+            encoder: crate::encoder::Encoder::with_code_books(code_books),
+        }
+    }
+
     /// runs the application's main loop until the user quits
     pub fn run(&mut self, terminal: &mut crate::tui::Tui) -> Result<()> {
         loop {
             match self.menu.run(terminal)?{
                     crate::menu::Choice::DecodeMode => {self.decoder.run(terminal)?;}
+                    crate::menu::Choice::EncodeMode => {self.encoder.run(terminal)?;}
                     crate::menu::Choice::CodeTable => {self.table.run(terminal)?;}
                     crate::menu::Choice::Shutdown => return Ok(()),
             };
@@ -0,0 +1,94 @@
+//! Just enough of the Unicode bidirectional algorithm to show Hebrew and
+//! Arabic text in a terminal, which draws every cell left to right.
+
+/// Whether `c` belongs to a right-to-left script.
+pub fn is_rtl(c: char) -> bool {
+    matches!(c,
+        '\u{0590}'..='\u{08FF}'
+        | '\u{FB1D}'..='\u{FDFF}'
+        | '\u{FE70}'..='\u{FEFF}'
+    )
+}
+
+/// Whether the first letter of `text` is written right to left.
+pub fn is_rtl_text(text: &str) -> bool {
+    text.chars().find(|c| c.is_alphanumeric()).is_some_and(is_rtl)
+}
+
+/// Reorders `text` from logical to visual order, line by line. The direction
+/// of a line is that of its first letter; runs in the other direction keep
+/// reading their own way.
+pub fn visual_order(text: &str) -> String {
+    text.lines()
+        .map(|line| {
+            let mut chars: Vec<char> = line.chars().collect();
+            let is_strong = |c: char| c.is_alphanumeric();
+            if is_rtl_text(line) {
+                chars.reverse();
+                reverse_runs(&mut chars, |c| is_strong(c) && !is_rtl(c));
+            } else {
+                reverse_runs(&mut chars, is_rtl);
+            }
+            chars.into_iter().collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Reverses every run of characters matching `member`, along with the
+/// neutral characters enclosed by the run.
+fn reverse_runs(chars: &mut [char], member: impl Fn(char) -> bool) {
+    let mut start = 0;
+    while start < chars.len() {
+        if !member(chars[start]) {
+            start += 1;
+            continue;
+        }
+        let mut end = start + 1;
+        let mut next = end;
+        while next < chars.len() && (member(chars[next]) || !chars[next].is_alphanumeric()) {
+            next += 1;
+            if member(chars[next - 1]) {
+                end = next;
+            }
+        }
+        chars[start..end].reverse();
+        start = end;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hebrew_line() {
+        assert!(is_rtl_text("שלום עולם"));
+        assert_eq!(visual_order("שלום עולם"), "םלוע םולש");
+    }
+
+    #[test]
+    fn hebrew_with_digits() {
+        assert_eq!(visual_order("שלום 73"), "73 םולש");
+        assert_eq!(visual_order("שלום 599 עולם"), "םלוע 599 םולש");
+    }
+
+    #[test]
+    fn latin_with_an_arabic_run() {
+        assert!(!is_rtl_text("cq سلام عليكم de"));
+        assert_eq!(visual_order("cq سلام عليكم de"), "cq مكيلع مالس de");
+    }
+
+    #[test]
+    fn neutrals_at_the_edges_of_a_run() {
+        // Neutrals inside a run move with it, those at its edges stay put.
+        assert_eq!(visual_order("ok (שלום, עולם)"), "ok (םלוע ,םולש)");
+        assert_eq!(visual_order("ok. שלום!"), "ok. םולש!");
+        assert_eq!(visual_order("? שלום ."), ". םולש ?");
+    }
+
+    #[test]
+    fn lines_keep_their_own_direction() {
+        assert_eq!(visual_order("שלום\nab"), "םולש\nab");
+    }
+}
@@ -0,0 +1,439 @@
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::morse::{self, Element, EncodedChar, MorseSymbol, Prosign, Token};
+use crate::trie::Trie;
+
+/// A bidirectional mapping between tokens and their morse codes.
+///
+/// Code books are read from plain text, one entry per line: the token, some
+/// whitespace, then the code written with `.` and `-`. American Morse also
+/// uses `_` for the long dash, `=` for the longer dash and a single space
+/// inside the code for a space. A token of two letters, like `ch`, is a
+/// digraph: its code decodes to both letters, but text is still encoded
+/// letter by letter. Prosigns are written by name between angle
+/// brackets. Blank lines and lines starting with `#` are ignored.
+///
+/// ```text
+/// # a tiny code book
+/// a .-
+/// b -...
+/// <AR> .-.-.
+/// ```
+#[derive(Debug, Clone)]
+pub struct CodeBook {
+    name: String,
+    entries: Vec<(Token, EncodedChar)>,
+    trie: Trie,
+}
+
+/// Why [`CodeBook::load`] failed: the file could not be read, or it is not
+/// a valid code book, in which case the [`morse::Error::Parse`] gives the line.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum LoadError {
+    Io(std::io::Error),
+    Parse(morse::Error),
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Io(error) => write!(f, "{}", error),
+            LoadError::Parse(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(error) => Some(error),
+            LoadError::Parse(error) => Some(error),
+        }
+    }
+}
+
+impl CodeBook {
+    /// Reads a code book from text. Errors give the line that could not be
+    /// read, counting from one.
+    pub fn parse(name: &str, text: &str) -> morse::Result<Self> {
+        let mut entries = vec![];
+        let mut trie = Trie::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let invalid = |reason: &str| morse::Error::Parse {
+                line: number + 1,
+                reason: reason.to_string(),
+            };
+            let (token, code) = line
+                .split_once(char::is_whitespace)
+                .ok_or_else(|| invalid("expected a token followed by its code"))?;
+            let token = parse_token(token).ok_or_else(|| invalid("unknown token"))?;
+            let symbols = parse_code(code.trim()).ok_or_else(|| invalid("codes may only contain '.', '-', '_', '=' and spaces"))?;
+            trie.insert(&symbols, token);
+            entries.push((token, symbols.into()));
+        }
+        Ok(Self {
+            name: name.to_string(),
+            entries,
+            trie,
+        })
+    }
+
+    /// Reads a code book from a file, naming it after the file.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, LoadError> {
+        let path = path.as_ref();
+        let name = path.file_stem().map_or_else(|| path.display().to_string(), |s| s.to_string_lossy().into_owned());
+        let text = std::fs::read_to_string(path).map_err(LoadError::Io)?;
+        Self::parse(&name, &text).map_err(LoadError::Parse)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the letters of this code book are written right to left.
+    pub fn is_rtl(&self) -> bool {
+        self.entries
+            .iter()
+            .any(|(token, _)| matches!(token, Token::Char(c) if crate::bidi::is_rtl(*c)))
+    }
+
+    /// Whether any code in this book contains `symbol`.
+    pub fn uses(&self, symbol: MorseSymbol) -> bool {
+        self.entries.iter().any(|(_, code)| code.symbols().contains(&symbol))
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &(Token, EncodedChar)> {
+        self.entries.iter()
+    }
+
+    /// Decodes a run of symbols. Characters that share their code with a
+    /// prosign, like `+` and AR, win over the prosign, so that text survives
+    /// a round trip through [`CodeBook::encode_str`].
+    pub fn decode_symbols(&self, symbols: &[MorseSymbol]) -> morse::Result<Token> {
+        let matching = self.trie.get(symbols);
+        matching
+            .iter()
+            .find(|token| !matches!(token, Token::Prosign(_)))
+            .or(matching.first())
+            .copied()
+            .ok_or_else(|| morse::Error::UnknownSequence(symbols.to_vec()))
+    }
+
+    /// Decodes a run of symbols like an operator on the air reads them, as
+    /// AR, BT and KN rather than `+`, `=` and `(`.
+    pub fn decode_symbols_prosigns_first(&self, symbols: &[MorseSymbol]) -> morse::Result<Token> {
+        self.decode_prosign(symbols)
+            .map_or_else(|| self.decode_symbols(symbols), |prosign| Ok(Token::Prosign(prosign)))
+    }
+
+    /// The prosign with this code, even if a character shares it.
+    pub fn decode_prosign(&self, symbols: &[MorseSymbol]) -> Option<Prosign> {
+        self.trie.get(symbols).iter().find_map(|token| match token {
+            Token::Prosign(prosign) => Some(*prosign),
+            _ => None,
+        })
+    }
+
+    /// Every token with this code, in the order of the code book.
+    pub fn matches(&self, symbols: &[MorseSymbol]) -> &[Token] {
+        self.trie.get(symbols)
+    }
+
+    /// Every token that can still be reached by keying more symbols after
+    /// `prefix`, including the one `prefix` itself decodes to.
+    pub fn candidates(&self, prefix: &[MorseSymbol]) -> Vec<Token> {
+        self.trie.reachable(prefix)
+    }
+
+    /// Encodes a token. Characters are matched regardless of case.
+    pub fn encode_token(&self, token: Token) -> morse::Result<EncodedChar> {
+        self.entries
+            .iter()
+            .find(|(t, _)| *t == token)
+            .or_else(|| self.entries.iter().find(|(t, _)| fold_case(*t) == fold_case(token)))
+            .map(|(_, code)| code.clone())
+            .ok_or(morse::Error::UnknownToken(token))
+    }
+
+    pub fn encode_character(&self, character: char) -> morse::Result<EncodedChar> {
+        self.encode_token(Token::Char(character))
+    }
+
+    /// Encodes a whole message, separating letters with letter gaps and
+    /// words with word gaps. Prosigns can be written by name, as in `<AR>`.
+    pub fn encode_str(&self, text: &str) -> morse::Result<Vec<Element>> {
+        self.encode_words(text, |token| self.encode_token(token).map(|code| vec![code]))
+    }
+
+    /// Encodes a message like [`CodeBook::encode_str`], sending characters
+    /// that have no code as their transliteration, so `ß` goes out as `ss`
+    /// and `ĥ` as the CH digraph where the book has one.
+    pub fn encode_str_transliterated(&self, text: &str) -> morse::Result<Vec<Element>> {
+        self.encode_words(text, |token| match (self.encode_token(token), token) {
+            (Err(error), Token::Char(c)) => self
+                .encode_spelling(morse::transliterate(c).ok_or(error.clone())?)
+                .map_err(|_| error),
+            (code, _) => code.map(|code| vec![code]),
+        })
+    }
+
+    /// The codes of a plain spelling, letter by letter unless the whole
+    /// spelling is a digraph of this book.
+    fn encode_spelling(&self, spelling: &str) -> morse::Result<Vec<EncodedChar>> {
+        let mut chars = spelling.chars();
+        if let (Some(a), Some(b), None) = (chars.next(), chars.next(), chars.next()) {
+            if let Ok(code) = self.encode_token(Token::Digraph(a, b)) {
+                return Ok(vec![code]);
+            }
+        }
+        spelling.chars().map(|c| self.encode_character(c)).collect()
+    }
+
+    /// Encodes each word with `encode`, which gives the codes of one token.
+    fn encode_words(
+        &self,
+        text: &str,
+        encode: impl Fn(Token) -> morse::Result<Vec<EncodedChar>>,
+    ) -> morse::Result<Vec<Element>> {
+        let mut elements = vec![];
+        for word in text.split_whitespace() {
+            let codes = tokenize(word)
+                .map(&encode)
+                .collect::<morse::Result<Vec<_>>>()?
+                .concat();
+            if !elements.is_empty() {
+                elements.push(Element::WordGap);
+            }
+            for (i, code) in codes.iter().enumerate() {
+                if i > 0 {
+                    elements.push(Element::LetterGap);
+                }
+                elements.extend(code.symbols().iter().copied().map(Element::Symbol));
+            }
+        }
+        Ok(elements)
+    }
+
+    /// Decodes a message produced by [`CodeBook::encode_str`], with a space
+    /// for every word gap.
+    pub fn decode_elements(&self, elements: &[Element]) -> morse::Result<String> {
+        self.decode_words(elements, |symbols| self.decode_symbols(symbols))
+    }
+
+    /// Decodes a message with prosigns first, so that `<AR>` written out by
+    /// [`CodeBook::encode_str`] comes back as `<AR>` rather than `+`.
+    pub fn decode_elements_prosigns_first(&self, elements: &[Element]) -> morse::Result<String> {
+        self.decode_words(elements, |symbols| self.decode_symbols_prosigns_first(symbols))
+    }
+
+    /// Decodes each character with `decode`, writing a space for every word gap.
+    fn decode_words(
+        &self,
+        elements: &[Element],
+        decode: impl Fn(&[MorseSymbol]) -> morse::Result<Token>,
+    ) -> morse::Result<String> {
+        let mut text = String::new();
+        let mut symbols = vec![];
+        for element in elements.iter().chain([&Element::LetterGap]) {
+            match element {
+                Element::Symbol(symbol) => symbols.push(*symbol),
+                Element::LetterGap | Element::WordGap => {
+                    if !symbols.is_empty() {
+                        text.push_str(&decode(&symbols)?.to_string());
+                    }
+                    symbols.clear();
+                    if *element == Element::WordGap {
+                        text.push(' ');
+                    }
+                }
+            }
+        }
+        Ok(text)
+    }
+}
+
+/// Lowercases characters whose lowercase form is a single character.
+fn fold_case(token: Token) -> Token {
+    match token {
+        Token::Char(c) => Token::Char(fold_char(c)),
+        Token::Digraph(a, b) => Token::Digraph(fold_char(a), fold_char(b)),
+        Token::Prosign(_) => token,
+    }
+}
+
+fn fold_char(c: char) -> char {
+    let mut lower = c.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => c,
+    }
+}
+
+/// Splits a word into tokens, reading `<NAME>` as a prosign. Digraphs are
+/// never joined, so `which` keeps its c and h.
+fn tokenize(word: &str) -> impl Iterator<Item = Token> + '_ {
+    let mut rest = word;
+    std::iter::from_fn(move || {
+        let prosign = rest
+            .strip_prefix('<')
+            .and_then(|r| r.split_once('>'))
+            .and_then(|(name, after)| Some((Prosign::from_name(name)?, after)));
+        if let Some((prosign, after)) = prosign {
+            rest = after;
+            return Some(Token::Prosign(prosign));
+        }
+        let mut chars = rest.chars();
+        let c = chars.next()?;
+        rest = chars.as_str();
+        Some(Token::Char(c))
+    })
+}
+
+fn parse_token(token: &str) -> Option<Token> {
+    if let Some(name) = token.strip_prefix('<').and_then(|t| t.strip_suffix('>')) {
+        return Prosign::from_name(name).map(Token::Prosign);
+    }
+    let mut chars = token.chars();
+    match (chars.next(), chars.next(), chars.next()) {
+        (Some(c), None, None) => Some(Token::Char(c)),
+        (Some(a), Some(b), None) => Some(Token::Digraph(a, b)),
+        _ => None,
+    }
+}
+
+fn parse_code(code: &str) -> Option<Vec<MorseSymbol>> {
+    code.chars()
+        .map(|c| match c {
+            '.' => Some(MorseSymbol::Dit),
+            '-' => Some(MorseSymbol::Dah),
+            '_' => Some(MorseSymbol::LongDah),
+            '=' => Some(MorseSymbol::LongerDah),
+            ' ' => Some(MorseSymbol::Space),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|symbols| !symbols.is_empty())
+}
+
+/// A list of code books with one of them selected, so views can switch
+/// alphabets at runtime.
+#[derive(Debug, Clone)]
+pub struct CodeBooks {
+    books: Vec<Arc<CodeBook>>,
+    selected: usize,
+    shifted: Option<Arc<CodeBook>>,
+}
+
+impl Default for CodeBooks {
+    fn default() -> Self {
+        Self::new(morse::built_in())
+    }
+}
+
+impl CodeBooks {
+    /// Selects the first of `books`, which must not be empty.
+    pub fn new(books: Vec<Arc<CodeBook>>) -> Self {
+        assert!(!books.is_empty(), "at least one code book is needed");
+        Self {
+            books,
+            selected: 0,
+            shifted: None,
+        }
+    }
+
+    pub fn current(&self) -> &Arc<CodeBook> {
+        self.shifted.as_ref().unwrap_or(&self.books[self.selected])
+    }
+
+    /// Selects the next code book, wrapping around after the last one.
+    pub fn next(&mut self) {
+        self.shifted = None;
+        self.selected = (self.selected + 1) % self.books.len();
+    }
+
+    /// Uses `book` until [`CodeBooks::unshift`], without changing the
+    /// selection. This is how the DO prosign enters Wabun.
+    pub fn shift(&mut self, book: Arc<CodeBook>) {
+        self.shifted = Some(book);
+    }
+
+    /// Returns to the selected code book.
+    pub fn unshift(&mut self) {
+        self.shifted = None;
+    }
+
+    pub fn is_shifted(&self) -> bool {
+        self.shifted.is_some()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BOOK: &str = "\
+# a tiny code book
+a .-
+b -...
+
+<AR> .-.-.
+";
+
+    #[test]
+    fn parse() {
+        let book = CodeBook::parse("Tiny", BOOK).unwrap();
+        assert_eq!(book.name(), "Tiny");
+        assert_eq!(book.entries().count(), 3);
+        assert_eq!(book.encode_character('B').unwrap().symbols(), parse_code("-...").unwrap());
+        assert_eq!(book.encode_token(Token::Prosign(Prosign::AR)).unwrap().symbols(), parse_code(".-.-.").unwrap());
+        assert_eq!(book.decode_elements(&book.encode_str("ab <AR> ba").unwrap()).unwrap(), "ab <AR> ba");
+        assert!(!book.is_rtl());
+        assert!(!book.uses(MorseSymbol::LongDah));
+    }
+
+    #[test]
+    fn load_keeps_the_parse_error() {
+        let path = std::env::temp_dir().join(format!("mo-test-{}.txt", std::process::id()));
+        std::fs::write(&path, "a .-\n\nb -..x\n").unwrap();
+        let error = CodeBook::load(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(matches!(error, LoadError::Parse(morse::Error::Parse { line: 3, .. })), "{:?}", error);
+        assert!(matches!(CodeBook::load(&path), Err(LoadError::Io(_))));
+    }
+
+    #[test]
+    fn unknown_tokens_and_sequences() {
+        let book = CodeBook::parse("Tiny", BOOK).unwrap();
+        assert_eq!(book.encode_str("abc"), Err(morse::Error::UnknownToken(Token::Char('c'))));
+        let dits = [MorseSymbol::Dit; 3];
+        assert_eq!(book.decode_symbols(&dits), Err(morse::Error::UnknownSequence(dits.to_vec())));
+    }
+
+    #[test]
+    fn shift_and_unshift() {
+        let mut books = CodeBooks::default();
+        let first = books.current().name().to_string();
+        books.shift(morse::wabun());
+        assert!(books.is_shifted());
+        assert_eq!(books.current().name(), "Wabun");
+        books.unshift();
+        assert!(!books.is_shifted());
+        assert_eq!(books.current().name(), first);
+    }
+
+    #[test]
+    fn parse_errors_give_the_line() {
+        let error = CodeBook::parse("Bad", "a .-\nb -..x\n").unwrap_err();
+        assert!(matches!(error, morse::Error::Parse { line: 2, .. }), "{:?}", error);
+        let error = CodeBook::parse("Bad", "\n<XYZ> .-\n").unwrap_err();
+        assert!(matches!(error, morse::Error::Parse { line: 2, .. }), "{:?}", error);
+        let error = CodeBook::parse("Bad", "a\n").unwrap_err();
+        assert!(matches!(error, morse::Error::Parse { line: 1, .. }), "{:?}", error);
+    }
+}
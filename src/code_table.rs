@@ -1,29 +1,75 @@
 use std::io::Result;
 use ratatui::{
     prelude::*,
-    widgets::{Table as RatatuiTable, Row, Widget},
+    widgets::{Table as RatatuiTable, Paragraph, Row, Widget},
     layout::Constraint,
 };
 use crossterm::event::{self, Event, KeyEventKind, KeyCode, KeyEvent};
 
+use crate::code_book::CodeBooks;
+use crate::morse::{EncodedChar, MorseSymbol, Token};
+use crate::notation::{Glyphs, Notation};
+
+/// Rows on a page; each row shows two entries.
+const ROWS_PER_PAGE: usize = 17;
+
 #[derive(Debug, Default)]
 pub struct Table {
     exit: bool,
+    page: usize,
+    code_books: CodeBooks,
+    glyphs: Glyphs,
 }
 
 impl Widget for &Table {
     fn render(self, area: Rect, buf: &mut Buffer)
         where Self: Sized 
     {
+        if crate::tap_code::is_tap_code(self.code_books.current()) {
+            return self.render_tap_code(area, buf);
+        }
+        let pages = self.pages();
+        let entries = pages.get(self.page).map_or(&[][..], Vec::as_slice);
+        let (left, right) = entries.split_at(entries.len().div_ceil(2));
+        // Right-to-left alphabets are mirrored: the code comes before the
+        // letter, and the first half of the entries is on the right.
+        let rtl = self.code_books.current().is_rtl();
+        let mirror = |mut cells: Vec<Line<'static>>| {
+            if rtl {
+                cells.reverse();
+            }
+            cells
+        };
+        let notation = Notation::with_glyphs(self.glyphs);
+        // Unicode codes are drawn with dits and dahs in their own colours.
+        let code = |symbols: &EncodedChar| match self.glyphs {
+            Glyphs::Unicode => Line::from(symbols.spans(0, Style::new().cyan(), Style::new().yellow())),
+            _ => Line::from(notation.format_symbols(symbols.symbols())),
+        };
         let mut rows = vec![];
-        for (c1, c2) in ('a'..='m').zip('n'..='z') {
-            let symbols1 = crate::morse::encode_character(c1).unwrap();
-            let symbols2 = crate::morse::encode_character(c2).unwrap();
-            rows.push(Row::new([format!("{}", c1), format!("{}", symbols1), c2.to_string(), format!("{}", symbols2)]));
+        for (i, (t1, symbols1)) in left.iter().enumerate() {
+            let mut cells = vec![Line::from(t1.to_string()), code(symbols1)];
+            match right.get(i) {
+                Some((t2, symbols2)) => cells.extend([Line::from(t2.to_string()), code(symbols2)]),
+                None => cells.extend([Line::default(), Line::default()]),
+            }
+            rows.push(Row::new(mirror(cells)));
         }
-        let widths = [Constraint::Length(10), Constraint::Length(20), Constraint::Length(10), Constraint::Length(20)];
-        let table = RatatuiTable::new(rows, widths);
-        Widget::render(table, area, buf)
+        let footer = Row::new([
+            format!("page {}/{}", self.page + 1, pages.len()),
+            self.code_books.current().name().to_string(),
+            String::new(),
+            self.glyphs.name().to_string(),
+        ]);
+        let mut widths = [Constraint::Length(10), Constraint::Length(30), Constraint::Length(10), Constraint::Length(30)];
+        if rtl {
+            widths.reverse();
+        }
+        let table = RatatuiTable::new(rows, widths).footer(footer);
+        let [table_area, help_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+        Widget::render(table, table_area, buf);
+        let help = Paragraph::new("<-/-> to turn page, a to change alphabet, g to change glyphs, q to exit.");
+        Widget::render(help, help_area, buf)
     }
 }
 
@@ -32,6 +78,63 @@ impl Table {
         Default::default()
     }
 
+    pub fn with_code_books(code_books: CodeBooks) -> Self {
+        Self {
+            exit: false,
+            page: 0,
+            code_books,
+            glyphs: Glyphs::default(),
+        }
+    }
+
+    /// Draws the tap code as its square, with the taps for each row and
+    /// column along the edges.
+    fn render_tap_code(&self, area: Rect, buf: &mut Buffer) {
+        let notation = Notation::with_glyphs(self.glyphs);
+        let taps = |count: usize| notation.format_symbols(&vec![MorseSymbol::Dit; count]);
+        let header = Row::new(std::iter::once(String::new()).chain((1..=5).map(taps)));
+        let rows = crate::tap_code::GRID.iter().enumerate().map(|(row, letters)| {
+            let cells = letters.iter().map(|letter| match letter {
+                'c' => "c/k".to_string(),
+                _ => letter.to_string(),
+            });
+            Row::new(std::iter::once(taps(row + 1)).chain(cells))
+        });
+        let footer = Row::new([
+            String::new(),
+            self.code_books.current().name().to_string(),
+            String::new(),
+            String::new(),
+            String::new(),
+            self.glyphs.name().to_string(),
+        ]);
+        let table = RatatuiTable::new(rows, [Constraint::Length(10); 6])
+            .header(header)
+            .footer(footer);
+        let [table_area, help_area] = Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(area);
+        Widget::render(table, table_area, buf);
+        let help = Paragraph::new("Tap the row, pause, then tap the column. a to change alphabet, g to change glyphs, q to exit.");
+        Widget::render(help, help_area, buf)
+    }
+
+    /// Splits the code book into pages, letters first and everything else after.
+    fn pages(&self) -> Vec<Vec<&(Token, EncodedChar)>> {
+        let (letters, others): (Vec<_>, Vec<_>) = self
+            .code_books
+            .current()
+            .entries()
+            .partition(|(token, _)| match token {
+                Token::Char(c) => c.is_alphabetic(),
+                Token::Digraph(..) => true,
+                Token::Prosign(_) => false,
+            });
+        [letters, others]
+            .iter()
+            .flat_map(|group| group.chunks(2 * ROWS_PER_PAGE))
+            .map(<[_]>::to_vec)
+            .collect()
+    }
+
     pub fn run(&mut self, terminal: &mut crate::tui::Tui) -> Result<()> {
         terminal.clear().expect("could not clear terminal");
         while !self.exit {
@@ -58,8 +161,16 @@ impl Table {
     }
 
     fn handle_key_event(&mut self, key_event: KeyEvent) {
-        if let KeyCode::Char('q') = key_event.code {
-            self.exit = true;
+        match key_event.code {
+            KeyCode::Char('q') => self.exit = true,
+            KeyCode::Char('a') => {
+                self.code_books.next();
+                self.page = 0;
+            }
+            KeyCode::Char('g') => self.glyphs = self.glyphs.next(),
+            KeyCode::Left => self.page = self.page.saturating_sub(1),
+            KeyCode::Right => self.page = (self.page + 1).min(self.pages().len().saturating_sub(1)),
+            _ => {}
         }
     }
 }
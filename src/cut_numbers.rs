@@ -0,0 +1,66 @@
+//! Cut numbers, the shortened digits of contest exchanges: a serial number
+//! like 109 goes out as `atn`, and `5nn` is the usual 599 report.
+
+use crate::morse::{self, Element, MorseSymbol, Token};
+
+/// Digits and the letters they are cut to. 4, 5 and 6 are sent in full.
+const CUTS: [(char, char); 7] = [
+    ('0', 't'),
+    ('1', 'a'),
+    ('2', 'u'),
+    ('3', 'v'),
+    ('7', 'b'),
+    ('8', 'd'),
+    ('9', 'n'),
+];
+
+/// The cut form of a digit; any other character is returned unchanged.
+pub fn cut(c: char) -> char {
+    CUTS.iter().find(|(digit, _)| *digit == c).map_or(c, |(_, letter)| *letter)
+}
+
+/// Reads a token in a numeric context, turning cut letters back into digits
+/// regardless of case. Anything else is returned unchanged.
+pub fn uncut(token: Token) -> Token {
+    match token {
+        Token::Char(c) => Token::Char(uncut_char(c)),
+        Token::Digraph(_, _) | Token::Prosign(_) => token,
+    }
+}
+
+fn uncut_char(c: char) -> char {
+    CUTS.iter()
+        .find(|(_, letter)| letter.eq_ignore_ascii_case(&c))
+        .map_or(c, |(digit, _)| *digit)
+}
+
+/// Encodes a number, or any text, with its digits cut.
+pub fn encode_cut_number(number: &str) -> morse::Result<Vec<Element>> {
+    morse::encode_str(&number.chars().map(cut).collect::<String>())
+}
+
+/// Decodes the symbols of one character of a numeric field.
+pub fn decode_cut_digit(symbols: &[MorseSymbol]) -> morse::Result<Token> {
+    morse::decode_symbols(symbols).map(uncut)
+}
+
+/// Decodes a numeric field, such as a serial number sent with cut digits.
+pub fn decode_cut_number(elements: &[Element]) -> morse::Result<String> {
+    Ok(morse::decode_elements(elements)?.chars().map(uncut_char).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cut_and_uncut() {
+        assert_eq!("5990".chars().map(cut).collect::<String>(), "5nnt");
+        assert_eq!(uncut(Token::Char('N')), Token::Char('9'));
+        assert_eq!(uncut(Token::Char('e')), Token::Char('e'));
+        let elements = encode_cut_number("109").unwrap();
+        assert_eq!(morse::decode_elements(&elements).unwrap(), "atn");
+        assert_eq!(decode_cut_number(&elements).unwrap(), "109");
+        assert_eq!(decode_cut_digit(&[MorseSymbol::Dah]).unwrap(), Token::Char('0'));
+    }
+}
@@ -1,37 +1,241 @@
 use std::io::Result;
+use std::ops::Range;
 use std::sync::{mpsc::channel, Arc, RwLock};
 use std::thread::sleep;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use layout::Offset;
 use ratatui::widgets::{Borders, Sparkline};
 use ratatui::{
     prelude::*,
     widgets::{List, Block, Paragraph},
 };
-use crossterm::event::{self, Event, KeyCode, KeyEventKind, MouseEventKind};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, ModifierKeyCode, MouseEventKind};
 
-use crate::morse::decode_symbols;
+use crate::code_book::CodeBooks;
+use crate::keying::{Decoded, GapThresholds, KeyingDecoder, MAX_WPM, MIN_WPM};
+use crate::morse::{Case, EncodedChar, MorseSymbol, Prosign, Token};
+use crate::notation::{Glyphs, Notation};
 use crate::ring::RingBuffer;
+use crate::tap_code::TapTiming;
+use crate::timing::Timing;
 
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct Decoder {
     state: Arc<RwLock<State>>,
 }
 
+impl Default for Decoder {
+    fn default() -> Self {
+        Self::with_code_books(CodeBooks::default())
+    }
+}
+
 #[derive(Debug)]
 enum Events {
-    Press,
-    Release,
+    /// The key went down, stamped when the input was read.
+    Press(Instant),
+    /// The key went up, stamped when the input was read.
+    Release(Instant),
     ClearMessage,
-    Tick,
+    NextCodeBook,
+    ChangeSpeed(f64),
+    ToggleCase,
+    ToggleNumeric,
+    ToggleTracking,
+    NextStraightKey,
+    TogglePlaceholder,
+    /// A sample of the key, stamped when it was taken.
+    Tick(Instant),
+}
+
+/// What is written in the message for a sequence that decodes to nothing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placeholder {
+    /// A fixed character, such as `�`.
+    Replacement(char),
+    /// The keyed pattern itself, such as `.-.-..`.
+    Raw,
+}
+
+impl Default for Placeholder {
+    fn default() -> Self {
+        Placeholder::Replacement('\u{FFFD}')
+    }
+}
+
+impl Placeholder {
+    fn render(&self, symbols: &[MorseSymbol]) -> String {
+        match self {
+            Placeholder::Replacement(c) => c.to_string(),
+            Placeholder::Raw => Notation::with_glyphs(Glyphs::Ascii).format_symbols(symbols),
+        }
+    }
+
+    fn toggle(self) -> Self {
+        match self {
+            Placeholder::Replacement(_) => Placeholder::Raw,
+            Placeholder::Raw => Placeholder::default(),
+        }
+    }
+}
+
+/// The keyboard key held down to key, like a straight key. Keying with the
+/// keyboard needs the terminal to report key releases, see
+/// [`crate::tui::reports_key_releases`]; the left mouse button always works.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StraightKey {
+    /// Either Ctrl key. Only terminals with the kitty keyboard protocol
+    /// report Ctrl on its own; crossterm drops it on Windows.
+    Ctrl,
+    /// The space bar, which then no longer pauses the decoder.
+    Space,
+}
+
+impl Default for StraightKey {
+    fn default() -> Self {
+        if StraightKey::Ctrl.is_available() {
+            StraightKey::Ctrl
+        } else {
+            StraightKey::Space
+        }
+    }
+}
+
+impl StraightKey {
+    /// Whether the terminal can report this key held down on its own.
+    fn is_available(&self) -> bool {
+        !(cfg!(windows) && *self == StraightKey::Ctrl)
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            StraightKey::Ctrl => "Ctrl",
+            StraightKey::Space => "space",
+        }
+    }
+
+    fn matches(&self, code: KeyCode) -> bool {
+        match self {
+            StraightKey::Ctrl => matches!(
+                code,
+                KeyCode::Modifier(ModifierKeyCode::LeftControl | ModifierKeyCode::RightControl)
+            ),
+            StraightKey::Space => code == KeyCode::Char(' '),
+        }
+    }
+
+    fn toggle(self) -> Self {
+        let next = match self {
+            StraightKey::Ctrl => StraightKey::Space,
+            StraightKey::Space => StraightKey::Ctrl,
+        };
+        if next.is_available() { next } else { self }
+    }
+}
+
+/// The decoded text, remembering which parts are placeholders for sequences
+/// that could not be decoded.
+#[derive(Debug, Default)]
+struct Message {
+    text: String,
+    unknown: Vec<Range<usize>>,
+}
+
+impl Message {
+    fn push_str(&mut self, text: &str) {
+        self.text.push_str(text);
+    }
+
+    fn push_unknown(&mut self, placeholder: &str) {
+        let start = self.text.len();
+        self.text.push_str(placeholder);
+        self.unknown.push(start..self.text.len());
+    }
+
+    /// Drops the last word, as requested by the error prosign.
+    fn erase_word(&mut self) {
+        let trimmed = self.text.trim_end();
+        let word_start = trimmed.rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        self.text.truncate(word_start);
+        self.unknown.retain(|range| range.end <= word_start);
+    }
+
+    fn clear(&mut self) {
+        *self = Default::default();
+    }
+
+    /// The message as a line, with placeholders highlighted and right-to-left
+    /// runs in visual order.
+    fn line(&self) -> Line<'static> {
+        let mut spans = vec![];
+        let mut start = 0;
+        for range in &self.unknown {
+            spans.push(Span::raw(crate::bidi::visual_order(&self.text[start..range.start])));
+            spans.push(Span::styled(self.text[range.clone()].to_string(), Style::new().red().reversed()));
+            start = range.end;
+        }
+        spans.push(Span::raw(crate::bidi::visual_order(&self.text[start..])));
+        if crate::bidi::is_rtl_text(&self.text) {
+            spans.reverse();
+        }
+        Line::from(spans)
+    }
 }
 
 #[derive(Debug, Default)]
 struct State {
     buf: RingBuffer,
-    message: String,
+    message: Message,
+    /// Every sequence that could not be decoded this session.
+    unknown: Vec<Vec<MorseSymbol>>,
+    placeholder: Placeholder,
+    straight_key: StraightKey,
+    keying: KeyingDecoder,
+    case: Case,
+    /// Whether the message is a numeric field, read with cut numbers.
+    numeric: bool,
+}
+
+impl State {
+    /// Writes what the keying decoder finished into the message.
+    fn record(&mut self, decoded: impl IntoIterator<Item = Decoded>) {
+        for decoded in decoded {
+            self.record_one(decoded);
+        }
+    }
+
+    fn record_one(&mut self, decoded: Decoded) {
+        match decoded {
+            Decoded::Token(Token::Prosign(Prosign::Error)) => self.message.erase_word(),
+            Decoded::Token(token) => {
+                let token = if self.numeric { crate::cut_numbers::uncut(token) } else { token };
+                let text = self.case.apply(token);
+                self.message.push_str(&text);
+            }
+            Decoded::Unknown(partial) => {
+                let placeholder = self.placeholder.render(&partial);
+                self.message.push_unknown(&placeholder);
+                self.unknown.push(partial);
+            }
+            Decoded::WordGap if !self.message.text.is_empty() => self.message.push_str(" "),
+            Decoded::WordGap => {}
+            Decoded::Symbol(_) | Decoded::Shift(_) => {}
+        }
+    }
 }
 
+/// The height of the help list and of the abbreviations panel beside it.
+const HELP_HEIGHT: u16 = 11;
+
+/// The width of the side panel explaining abbreviations.
+const PANEL_WIDTH: u16 = 45;
+
+/// How much +/- changes the beat of the tap code by.
+const TAP_STEP: Duration = Duration::from_millis(10);
+
+/// How often the key is sampled for the display.
+const TICK: Duration = Duration::from_millis(16);
+
 impl Widget for &Decoder {
 
     fn render(self, area: Rect, buf: &mut Buffer)
@@ -42,14 +246,60 @@ impl Widget for &Decoder {
             y: area.y as i32,
         };
 
-        let list =List::new(["c to clear.", "<space> to pause.", "q to exit.", "Dah, dah, dit, dah!"]);
-        let list_area = Rect::new(0, 0, 100, 10).offset(offset);
+        let lock: std::sync::RwLockReadGuard<State> = self.state.read().unwrap();
+        let mut alphabet = format!("a to change alphabet ({}).", lock.keying.code_books().current().name());
+        if crate::tap_code::is_tap_code(lock.keying.code_books().current()) {
+            alphabet.push_str(" Tap the row, pause, then tap the column.");
+        }
+        let speed = if lock.keying.is_tapping() {
+            format!("+/- to change speed ({} ms per tap).", lock.keying.tap_timing().beat().as_millis())
+        } else {
+            format!("+/- to change speed ({:.0} WPM).", lock.keying.timing().character_wpm())
+        };
+        let tracking = format!("s to toggle speed tracking ({}).", if lock.keying.is_tracking() { "on" } else { "off" });
+        let case = format!("u to toggle case ({:?}).", lock.case);
+        let numeric = format!("n to toggle cut numbers ({}).", if lock.numeric { "on" } else { "off" });
+        let key_releases = crate::tui::reports_key_releases();
+        let pause = if key_releases && lock.straight_key == StraightKey::Space { "<space> keys rather than pauses." } else { "<space> to pause." };
+        let keying = if key_releases && !StraightKey::Ctrl.is_available() {
+            "Hold space or the left mouse button to key; Windows does not report Ctrl alone.".to_string()
+        } else if key_releases {
+            format!("k to change straight key (hold {} or the left mouse button).", lock.straight_key.name())
+        } else {
+            "Hold the left mouse button to key; this terminal does not report key releases.".to_string()
+        };
+        let placeholder = format!(
+            "p to toggle placeholders ({}, {} undecodable so far).",
+            lock.placeholder.render(&[MorseSymbol::Dit, MorseSymbol::Dah]),
+            lock.unknown.len(),
+        );
+        let list =List::new(["c to clear.", pause, keying.as_str(), alphabet.as_str(), speed.as_str(), tracking.as_str(), case.as_str(), numeric.as_str(), placeholder.as_str(), "q to exit.", "Dah, dah, dit, dah!"]);
+        let list_area = Rect::new(0, 0, area.width.saturating_sub(PANEL_WIDTH), HELP_HEIGHT).offset(offset);
         Widget::render(list, list_area, buf);
 
-        let lock: std::sync::RwLockReadGuard<State> = self.state.read().unwrap();
-        let spark_area = Rect::new(0, 8, buf.area().width as u16, 3).offset(offset);
+        // Q-codes and abbreviations found in the message, explained.
+        let annotations: Vec<String> = crate::abbreviations::annotate(&lock.message.text)
+            .into_iter()
+            .map(|(word, meaning)| format!("{}: {}", word, meaning))
+            .collect();
+        let panel = List::new(annotations)
+        .block(Block::bordered().title("Abbreviations"));
+        let panel_area = Rect::new(area.width.saturating_sub(PANEL_WIDTH), 0, PANEL_WIDTH.min(area.width), HELP_HEIGHT).offset(offset);
+        Widget::render(panel, panel_area, buf);
+
+        let spark_area = Rect::new(0, HELP_HEIGHT, buf.area().width, 3).offset(offset);
         let data: Vec<_> = lock.buf.iter().map(|dp| if dp {1u64} else { 0u64}).collect();
-        let text = lock.message.clone();
+        let text = lock.message.line();
+        let candidates: Vec<String> = lock
+            .keying
+            .code_books()
+            .current()
+            .candidates(lock.keying.partial())
+            .iter()
+            .map(Token::to_string)
+            .collect();
+        let partial = EncodedChar::from(lock.keying.partial().to_vec()).spans(0, Style::new().cyan(), Style::new().yellow());
+        let partial_empty = lock.keying.partial().is_empty();
         drop(lock);
 
         let spark = Sparkline::default()
@@ -66,6 +316,20 @@ impl Widget for &Decoder {
         let text_area = Rect::new(0, 0, area.width, 1).offset(offset);
         Widget::render(text, text_area, buf);
 
+        // Where the character being keyed sits in the code tree: its symbols
+        // so far and every character still reachable from them.
+        let progress = if partial_empty {
+            Line::default()
+        } else {
+            let mut spans = partial;
+            spans.push(Span::raw(format!(" → {}", candidates.join(" "))));
+            Line::from(spans)
+        };
+        let progress = Paragraph::new(progress)
+        .alignment(Alignment::Center);
+        let progress_area = Rect::new(0, 2, area.width, 1).offset(offset);
+        Widget::render(progress, progress_area, buf);
+
     }
 }
 
@@ -74,12 +338,44 @@ impl Decoder {
         Default::default()
     }
 
+    pub fn with_code_books(code_books: CodeBooks) -> Self {
+        let mut keying = KeyingDecoder::new(code_books, Timing::default());
+        keying.set_prosigns_first(true);
+        let state = State {
+            keying,
+            ..Default::default()
+        };
+        Self {
+            state: Arc::new(RwLock::new(state)),
+        }
+    }
+
+    /// Sets how long a pause ends a character and a word.
+    pub fn set_gaps(&mut self, gaps: GapThresholds) {
+        self.state.write().unwrap().keying.set_gaps(gaps);
+    }
+
+    /// Sets the keyboard key used as a straight key.
+    pub fn set_straight_key(&mut self, straight_key: StraightKey) {
+        self.state.write().unwrap().straight_key = straight_key;
+    }
+
+    /// Sets what undecodable sequences are shown as.
+    pub fn set_placeholder(&mut self, placeholder: Placeholder) {
+        self.state.write().unwrap().placeholder = placeholder;
+    }
+
     pub fn run(&mut self, terminal: &mut crate::tui::Tui) -> Result<()> {
         terminal.clear().expect("terminal coudln't be cleared");
 
         let width = terminal.size()?.width;
         self.state.write().unwrap().buf = RingBuffer::new(width as usize);
 
+        // The keying decoder times the key from `start`, so whatever it held
+        // from an earlier run is dropped.
+        self.state.write().unwrap().keying.reset();
+        let start = Instant::now();
+
         let paused = std::sync::atomic::AtomicBool::new(false);
         let shutdown = std::sync::atomic::AtomicBool::new(false);
         std::thread::scope(|s| {
@@ -95,56 +391,53 @@ impl Decoder {
                     return Ok::<(), std::io::Error>(());
                 }
                 if !paused_ref.load(Ordering::Relaxed) {
-                    sender.send(Events::Tick).unwrap();
+                    sender.send(Events::Tick(Instant::now())).unwrap();
                 }
-                sleep(Duration::from_millis(16));
+                sleep(TICK);
             });
 
             // Model thread
             let cloned_state = Arc::clone(&self.state);
             s.spawn(move || {
-                let mut pressed = false;
-                let mut unpressed_ticks = 0;
-                let mut pressed_ticks = 0;
-                let mut partial_symbol = vec![];
+                // Ticks, presses and releases are timed by their stamps, which
+                // the channel keeps in order, so a thread that falls behind
+                // only delays the display and the end of a character.
                 for e in receiver.iter() {
+                    let mut state = cloned_state.write().unwrap();
                     match e {
-                        Events::Tick => {
-                            let mut state = cloned_state.write().unwrap();
-                            state.buf.sample(pressed); 
-                            if pressed {
-                                pressed_ticks += 1;
-                                unpressed_ticks = 0;
-                            } else {
-                                unpressed_ticks += 1;
-                                if pressed_ticks > 0 {
-                                    partial_symbol.push(
-                                        if pressed_ticks < 7 {
-                                            crate::morse::MorseSymbol::Dit
-                                        } else {
-                                            crate::morse::MorseSymbol::Dah
-                                        }
-                                    );
-                                } else if unpressed_ticks > 10 {
-                                    let decoded = decode_symbols(&partial_symbol);
-                                    partial_symbol = vec![];
-                                    match decoded {
-                                        None => (),
-                                        Some(c) => state.message.push(c),
-                                    };
-                                };
-                                pressed_ticks = 0;
-                            }
+                        Events::Tick(at) => {
+                            let pressed = state.keying.is_pressed();
+                            state.buf.sample(pressed);
+                            let decoded = state.keying.update(at.duration_since(start));
+                            state.record(decoded);
                         }
-                        Events::Press => {
-                            pressed = true;
+                        Events::Press(at) => {
+                            let decoded = state.keying.press(at.duration_since(start));
+                            state.record(decoded);
                         }
-                        Events::Release => {
-                            pressed = false;
+                        Events::Release(at) => {
+                            let decoded = state.keying.release(at.duration_since(start));
+                            state.record(decoded);
                         }
-                        Events::ClearMessage => {
-                            let mut state = cloned_state.write().unwrap();
-                            state.message = String::new();
+                        Events::ClearMessage => state.message.clear(),
+                        Events::NextCodeBook => state.keying.code_books_mut().next(),
+                        Events::TogglePlaceholder => state.placeholder = state.placeholder.toggle(),
+                        Events::ToggleCase => state.case = state.case.toggle(),
+                        Events::ToggleNumeric => state.numeric = !state.numeric,
+                        Events::NextStraightKey => state.straight_key = state.straight_key.toggle(),
+                        Events::ToggleTracking => {
+                            let tracking = state.keying.is_tracking();
+                            state.keying.set_tracking(!tracking);
+                        }
+                        // Faster tapping is a shorter beat.
+                        Events::ChangeSpeed(delta) if state.keying.is_tapping() => {
+                            let beat = state.keying.tap_timing().beat().as_secs_f64() - delta * TAP_STEP.as_secs_f64();
+                            state.keying.set_tap_timing(TapTiming::new(Duration::from_secs_f64(beat.max(0.0))));
+                        }
+                        Events::ChangeSpeed(delta) => {
+                            let wpm = (state.keying.timing().character_wpm() + delta).clamp(MIN_WPM, MAX_WPM);
+                            let timing = state.keying.timing().at_character_wpm(wpm);
+                            state.keying.set_timing(timing);
                         }
                     }
                 };
@@ -162,8 +455,19 @@ impl Decoder {
                 }
             });
 
+            let key_releases = crate::tui::reports_key_releases();
             loop {
-                match event::read()? {
+                let event = event::read()?;
+                let at = Instant::now();
+                let straight_key = self.state.read().unwrap().straight_key;
+                match event {
+                    Event::Key(key_event) if key_releases && straight_key.matches(key_event.code) => {
+                        match key_event.kind {
+                            KeyEventKind::Press => sender_keys.send(Events::Press(at)).unwrap(),
+                            KeyEventKind::Release => sender_keys.send(Events::Release(at)).unwrap(),
+                            KeyEventKind::Repeat => {}
+                        }
+                    }
                     // it's important to check that the event is a key press event as
                     // crossterm also emits key release and repeat events on Windows.
                     Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
@@ -173,6 +477,14 @@ impl Decoder {
                                 return Ok(());
                             }
                             KeyCode::Char('c') => sender_keys.send(Events::ClearMessage).unwrap(),
+                            KeyCode::Char('a') => sender_keys.send(Events::NextCodeBook).unwrap(),
+                            KeyCode::Char('p') => sender_keys.send(Events::TogglePlaceholder).unwrap(),
+                            KeyCode::Char('u') => sender_keys.send(Events::ToggleCase).unwrap(),
+                            KeyCode::Char('n') => sender_keys.send(Events::ToggleNumeric).unwrap(),
+                            KeyCode::Char('s') => sender_keys.send(Events::ToggleTracking).unwrap(),
+                            KeyCode::Char('k') => sender_keys.send(Events::NextStraightKey).unwrap(),
+                            KeyCode::Char('+') => sender_keys.send(Events::ChangeSpeed(1.0)).unwrap(),
+                            KeyCode::Char('-') => sender_keys.send(Events::ChangeSpeed(-1.0)).unwrap(),
                             KeyCode::Char(' ') => {
                                 paused_ref.fetch_xor(true, Ordering::Relaxed);
                             }
@@ -182,10 +494,10 @@ impl Decoder {
                     Event::Mouse(mouse_event) => {
                         match mouse_event.kind {
                             MouseEventKind::Down(event::MouseButton::Left) => {
-                                sender_keys.send(Events::Press).unwrap();
+                                sender_keys.send(Events::Press(at)).unwrap();
                             }
                             MouseEventKind::Up(event::MouseButton::Left) => {
-                                sender_keys.send(Events::Release).unwrap();   
+                                sender_keys.send(Events::Release(at)).unwrap();   
                             }
                             _ => {}
                         }
@@ -200,3 +512,58 @@ impl Decoder {
         frame.render_widget(self,frame.size())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chars(text: &str) -> Vec<Decoded> {
+        text.chars()
+            .map(|c| if c == ' ' { Decoded::WordGap } else { Decoded::Token(Token::Char(c)) })
+            .collect()
+    }
+
+    #[test]
+    fn error_prosign_erases_the_last_word() {
+        let mut state = State::default();
+        state.record(chars("cq de pa"));
+        state.record([Decoded::Token(Token::Prosign(Prosign::Error))]);
+        assert_eq!(state.message.text, "cq de ");
+        state.record(chars("paris"));
+        assert_eq!(state.message.text, "cq de paris");
+    }
+
+    #[test]
+    fn case() {
+        let mut state = State { case: Case::Upper, ..Default::default() };
+        state.record(chars("cq de"));
+        assert_eq!(state.message.text, "CQ DE");
+        state.case = state.case.toggle();
+        state.record(chars(" DL"));
+        assert_eq!(state.message.text, "CQ DE dl");
+    }
+
+    #[test]
+    fn unknown_sequences_get_placeholders() {
+        let mut state = State::default();
+        let dits = vec![MorseSymbol::Dit; 9];
+        state.record([Decoded::WordGap, Decoded::Unknown(dits.clone())]);
+        assert_eq!(state.message.text, "\u{FFFD}");
+        assert_eq!(state.unknown.len(), 1);
+        state.record([Decoded::Token(Token::Prosign(Prosign::Error))]);
+        assert!(state.message.unknown.is_empty());
+        state.placeholder = Placeholder::Raw;
+        state.record([Decoded::Unknown(dits)]);
+        assert_eq!(state.message.text, ".........");
+    }
+
+    #[test]
+    fn numeric_mode_uncuts_digits() {
+        let mut state = State { numeric: true, ..Default::default() };
+        state.record(chars("5nn tu"));
+        assert_eq!(state.message.text, "599 02");
+        state.numeric = false;
+        state.record(chars(" tu"));
+        assert_eq!(state.message.text, "599 02 tu");
+    }
+}
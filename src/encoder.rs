@@ -0,0 +1,118 @@
+use std::io::Result;
+use std::time::Duration;
+use ratatui::{
+    prelude::*,
+    widgets::{List, Paragraph, Widget, Wrap},
+};
+use crossterm::event::{self, Event, KeyEventKind, KeyCode, KeyEvent};
+
+use crate::code_book::CodeBooks;
+use crate::morse::MorseSymbol;
+use crate::notation::Notation;
+use crate::tap_code::TapTiming;
+use crate::timing::Timing;
+
+/// Encodes typed text as it is entered.
+#[derive(Debug, Default)]
+pub struct Encoder {
+    exit: bool,
+    input: String,
+    notation: Notation,
+    code_books: CodeBooks,
+}
+
+impl Widget for &Encoder {
+    fn render(self, area: Rect, buf: &mut Buffer)
+        where Self: Sized
+    {
+        let [help_area, input_area, output_area] = Layout::vertical([
+            Constraint::Length(4),
+            Constraint::Length(2),
+            Constraint::Min(0),
+        ]).areas(area);
+
+        let alphabet = format!("<shift+tab> to change alphabet ({}).", self.code_books.current().name());
+        let glyphs = format!("<tab> to change glyphs ({}).", self.notation.glyphs.name());
+        let list = List::new(["Type to encode.", alphabet.as_str(), glyphs.as_str(), "<esc> to exit."]);
+        Widget::render(list, help_area, buf);
+
+        let input = Paragraph::new(format!("> {}", self.input));
+        Widget::render(input, input_area, buf);
+
+        let output = match self.code_books.current().encode_str_transliterated(&self.input) {
+            Ok(elements) => {
+                // The tap code is tapped to a rhythm of its own.
+                let (timeline, speed) = if self.code_books.current().uses(MorseSymbol::Dah) {
+                    let timing = Timing::default();
+                    (timing.timeline(&elements), format!("{} WPM", timing.character_wpm()))
+                } else {
+                    let taps = TapTiming::default();
+                    (taps.timeline(&elements), format!("{} ms per tap", taps.beat().as_millis()))
+                };
+                let duration: Duration = timeline.iter().map(|(_, d)| *d).sum();
+                format!(
+                    "{}\n\n{:.1}s at {}",
+                    self.notation.format(&elements),
+                    duration.as_secs_f64(),
+                    speed,
+                )
+            }
+            Err(error) => error.to_string(),
+        };
+        let output = Paragraph::new(output)
+        .wrap(Wrap { trim: false });
+        Widget::render(output, output_area, buf);
+    }
+}
+
+impl Encoder {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    pub fn with_code_books(code_books: CodeBooks) -> Self {
+        Self {
+            code_books,
+            ..Default::default()
+        }
+    }
+
+    pub fn run(&mut self, terminal: &mut crate::tui::Tui) -> Result<()> {
+        terminal.clear().expect("could not clear terminal");
+        while !self.exit {
+            terminal.draw(|frame| self.render_frame(frame))?;
+            self.handle_events()?;
+        }
+        self.exit = false;
+        Ok(())
+    }
+
+    fn render_frame(&self, frame: &mut Frame) {
+        frame.render_widget(self,frame.size())
+    }
+
+    fn handle_events(&mut self) -> Result<()> {
+        match event::read()? {
+            // it's important to check that the event is a key press event as
+            // crossterm also emits key release and repeat events on Windows.
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
+                self.handle_key_event(key_event)
+            }
+            _ => {}
+        };
+        Ok(())
+    }
+
+    fn handle_key_event(&mut self, key_event: KeyEvent) {
+        match key_event.code {
+            KeyCode::Esc => self.exit = true,
+            KeyCode::Tab => self.notation.glyphs = self.notation.glyphs.next(),
+            KeyCode::BackTab => self.code_books.next(),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) => self.input.push(c),
+            _ => {}
+        }
+    }
+}
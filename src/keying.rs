@@ -0,0 +1,613 @@
+//! Decoding a hand-keyed signal: a [`KeyingDecoder`] is told when the key
+//! goes down and up, and works out the symbols and characters from the
+//! timings. It keeps no clock of its own, so any front end, or a test, can
+//! drive it with timestamps measured from a start of its choosing.
+//!
+//! ```
+//! use std::time::Duration;
+//! use mo::keying::{Decoded, KeyingDecoder};
+//! use mo::morse::{MorseSymbol, Token};
+//!
+//! // At the default 20 WPM a dit is 60 ms long.
+//! let ms = Duration::from_millis;
+//! let mut decoder = KeyingDecoder::default();
+//! decoder.press(ms(0));
+//! assert_eq!(decoder.release(ms(60)), Some(Decoded::Symbol(MorseSymbol::Dit)));
+//! decoder.press(ms(120));
+//! assert_eq!(decoder.release(ms(300)), Some(Decoded::Symbol(MorseSymbol::Dah)));
+//! assert!(decoder.update(ms(400)).is_empty());
+//! assert_eq!(decoder.update(ms(500)), [Decoded::Token(Token::Char('a'))]);
+//! assert_eq!(decoder.update(ms(700)), [Decoded::WordGap]);
+//! ```
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use crate::code_book::{CodeBook, CodeBooks};
+use crate::morse::{MorseSymbol, Prosign, Token};
+use crate::tap_code::TapTiming;
+use crate::timing::Timing;
+
+/// What the decoder made of the key so far.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Decoded {
+    /// A symbol of the character being keyed.
+    Symbol(MorseSymbol),
+    /// A finished character or prosign.
+    Token(Token),
+    /// A prosign that switched alphabets rather than being part of the text.
+    Shift(Prosign),
+    /// A finished sequence that is not the code of anything.
+    Unknown(Vec<MorseSymbol>),
+    /// A pause long enough to end the word.
+    WordGap,
+}
+
+/// How long the key must stay up to end a character and to end a word, as
+/// a fraction of the way from one nominal gap of the [`Timing`] to the next:
+/// `letter` from the longest gap inside a character to the letter gap, and
+/// `word` from the letter gap to the word gap. The thresholds so follow the
+/// estimated speed and any Farnsworth spacing.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GapThresholds {
+    pub letter: f64,
+    pub word: f64,
+}
+
+/// Halfway between the nominal gaps.
+impl Default for GapThresholds {
+    fn default() -> Self {
+        Self {
+            letter: 0.5,
+            word: 0.5,
+        }
+    }
+}
+
+/// The speeds the decoder follows an operator between, in words per minute.
+pub const MIN_WPM: f64 = 5.0;
+pub const MAX_WPM: f64 = 40.0;
+
+/// How many of the latest marks the speed is estimated from.
+const SPEED_WINDOW: usize = 8;
+
+/// Turns the timings of a key into symbols and tokens.
+///
+/// Unless speed tracking is turned off, the timing follows the operator: the
+/// length of a dit is estimated from the latest marks, so sending faster or
+/// slower than the set speed still decodes.
+#[derive(Debug, Clone)]
+pub struct KeyingDecoder {
+    code_books: CodeBooks,
+    timing: Timing,
+    /// The rhythm of code books without dahs, like the tap code.
+    taps: TapTiming,
+    tracking: bool,
+    speed: SpeedTracker,
+    gaps: GapThresholds,
+    /// Whether prosigns win over the characters that share their code.
+    prosigns_first: bool,
+    /// The symbols keyed so far for the character in progress.
+    partial: Vec<MorseSymbol>,
+    /// Whether a character was finished since the last word gap.
+    in_word: bool,
+    pressed_at: Option<Duration>,
+    released_at: Option<Duration>,
+}
+
+impl Default for KeyingDecoder {
+    fn default() -> Self {
+        Self::new(CodeBooks::default(), Timing::default())
+    }
+}
+
+impl KeyingDecoder {
+    pub fn new(code_books: CodeBooks, timing: Timing) -> Self {
+        Self {
+            code_books,
+            timing,
+            taps: TapTiming::default(),
+            tracking: true,
+            speed: SpeedTracker::default(),
+            gaps: GapThresholds::default(),
+            prosigns_first: false,
+            partial: vec![],
+            in_word: false,
+            pressed_at: None,
+            released_at: None,
+        }
+    }
+
+    pub fn code_books(&self) -> &CodeBooks {
+        &self.code_books
+    }
+
+    pub fn code_books_mut(&mut self) -> &mut CodeBooks {
+        &mut self.code_books
+    }
+
+    pub fn timing(&self) -> Timing {
+        self.timing
+    }
+
+    /// Sets the speed, which speed tracking then starts from.
+    pub fn set_timing(&mut self, timing: Timing) {
+        self.timing = timing;
+        self.speed = SpeedTracker::default();
+    }
+
+    pub fn tap_timing(&self) -> TapTiming {
+        self.taps
+    }
+
+    pub fn set_tap_timing(&mut self, taps: TapTiming) {
+        self.taps = taps;
+    }
+
+    /// Whether the current code book is tapped rather than keyed, so that
+    /// every press is a dit and the [`TapTiming`] applies.
+    pub fn is_tapping(&self) -> bool {
+        !self.code_books.current().uses(MorseSymbol::Dah)
+    }
+
+    pub fn is_tracking(&self) -> bool {
+        self.tracking
+    }
+
+    pub fn set_tracking(&mut self, tracking: bool) {
+        self.tracking = tracking;
+    }
+
+    pub fn gaps(&self) -> GapThresholds {
+        self.gaps
+    }
+
+    pub fn set_gaps(&mut self, gaps: GapThresholds) {
+        self.gaps = gaps;
+    }
+
+    /// Decodes AR, BT and KN as prosigns rather than as `+`, `=` and `(`,
+    /// as an operator on the air would read them.
+    pub fn set_prosigns_first(&mut self, prosigns_first: bool) {
+        self.prosigns_first = prosigns_first;
+    }
+
+    pub fn partial(&self) -> &[MorseSymbol] {
+        &self.partial
+    }
+
+    pub fn is_pressed(&self) -> bool {
+        self.pressed_at.is_some()
+    }
+
+    /// Forgets the key and the character in progress, so that the next
+    /// press can be timed from a new start. The code books and the speed
+    /// are kept.
+    pub fn reset(&mut self) {
+        self.partial.clear();
+        self.in_word = false;
+        self.pressed_at = None;
+        self.released_at = None;
+    }
+
+    /// The key went down. A long enough gap since the last release finishes
+    /// the character in progress, and the word, unless [`KeyingDecoder::update`]
+    /// already did. A shorter one puts a space in the character for code
+    /// books that have spaces inside characters.
+    pub fn press(&mut self, at: Duration) -> Vec<Decoded> {
+        if self.is_pressed() {
+            return vec![];
+        }
+        self.pressed_at = Some(at);
+        let Some(released_at) = self.released_at else {
+            return vec![];
+        };
+        let gap = at.saturating_sub(released_at);
+        let mut decoded = self.end_gaps(gap);
+        if !self.partial.is_empty() && self.classifier().is_space(gap) {
+            self.partial.push(MorseSymbol::Space);
+            decoded.push(Decoded::Symbol(MorseSymbol::Space));
+        }
+        decoded
+    }
+
+    /// The key went up, completing a symbol.
+    pub fn release(&mut self, at: Duration) -> Option<Decoded> {
+        let pressed = at.saturating_sub(self.pressed_at.take()?);
+        self.released_at = Some(at);
+        // The length of a tap says nothing about the speed.
+        if self.tracking && self.classifier().dahs {
+            let longest = if self.classifier().long_dahs { 7 } else { 3 };
+            let unit = self.speed.track(pressed, self.timing.unit(), longest);
+            let wpm = (1.2 / unit.as_secs_f64()).clamp(MIN_WPM, MAX_WPM);
+            self.timing = self.timing.at_character_wpm(wpm);
+        }
+        let symbol = self.classifier().mark(pressed);
+        self.partial.push(symbol);
+        Some(Decoded::Symbol(symbol))
+    }
+
+    /// Lets the decoder know the time while the key is up, so it can finish
+    /// the character in progress and the word once the gap is long enough.
+    pub fn update(&mut self, now: Duration) -> Vec<Decoded> {
+        match self.released_at {
+            Some(released_at) if !self.is_pressed() => self.end_gaps(now.saturating_sub(released_at)),
+            _ => vec![],
+        }
+    }
+
+    /// Finishes the character and the word that a gap this long ends.
+    fn end_gaps(&mut self, gap: Duration) -> Vec<Decoded> {
+        let classifier = self.classifier();
+        let mut decoded = vec![];
+        if !self.partial.is_empty() && classifier.ends_character(gap) {
+            decoded.push(self.finish());
+        }
+        if self.in_word && self.partial.is_empty() && classifier.ends_word(gap) {
+            self.in_word = false;
+            decoded.push(Decoded::WordGap);
+        }
+        decoded
+    }
+
+    fn classifier(&self) -> Classifier {
+        Classifier::new(self.timing, self.taps, self.gaps, self.code_books.current())
+    }
+
+    /// Decodes the character in progress, following the prosigns that
+    /// switch into and out of Wabun.
+    fn finish(&mut self) -> Decoded {
+        let partial = std::mem::take(&mut self.partial);
+        self.in_word = true;
+        let book = self.code_books.current();
+        let decoded = if self.prosigns_first {
+            book.decode_symbols_prosigns_first(&partial)
+        } else {
+            book.decode_symbols(&partial)
+        };
+        match decoded {
+            Err(_) => Decoded::Unknown(partial),
+            Ok(Token::Prosign(Prosign::DO)) => {
+                self.code_books.shift(crate::morse::wabun());
+                Decoded::Shift(Prosign::DO)
+            }
+            Ok(Token::Prosign(Prosign::SN)) if self.code_books.is_shifted() => {
+                self.code_books.unshift();
+                Decoded::Shift(Prosign::SN)
+            }
+            Ok(token) => Decoded::Token(token),
+        }
+    }
+}
+
+/// Turns key timings into symbols, cutting between the nominal lengths of
+/// a [`Timing`], the same lengths [`Timing::timeline`] keys. The long dashes
+/// and the spaces inside characters are only recognised for code books that
+/// use them. In a code book without dahs, like the tap code, every press is
+/// a dit and the gaps are those of the [`TapTiming`].
+#[derive(Debug, Clone, Copy)]
+struct Classifier {
+    timing: Timing,
+    dahs: bool,
+    long_dahs: bool,
+    /// The longest gaps that still belong inside a character, between
+    /// symbols and, for code books that have them, at a space.
+    space: Option<Duration>,
+    letter: Duration,
+    word: Duration,
+}
+
+impl Classifier {
+    fn new(timing: Timing, taps: TapTiming, gaps: GapThresholds, code_book: &CodeBook) -> Self {
+        let dahs = code_book.uses(MorseSymbol::Dah);
+        let spaces = code_book.uses(MorseSymbol::Space);
+        // The nominal gaps between symbols, at a space, between letters and
+        // between words.
+        let (symbol, space, letter, word) = if dahs {
+            (timing.unit(), timing.symbol(MorseSymbol::Space), timing.letter_gap(), timing.word_gap())
+        } else {
+            (taps.tap_gap(), taps.group_gap(), taps.letter_gap(), taps.word_gap())
+        };
+        let inside = if spaces { space } else { symbol };
+        Self {
+            timing,
+            dahs,
+            long_dahs: code_book.uses(MorseSymbol::LongDah) || code_book.uses(MorseSymbol::LongerDah),
+            space: spaces.then(|| between(symbol, space, 0.5)),
+            letter: between(inside, letter, gaps.letter),
+            word: between(letter, word, gaps.word),
+        }
+    }
+
+    /// Picks the symbol whose nominal length is closest to `pressed`.
+    fn mark(&self, pressed: Duration) -> MorseSymbol {
+        let cut = |short, long| between(self.timing.symbol(short), self.timing.symbol(long), 0.5);
+        match pressed {
+            d if !self.dahs || d < cut(MorseSymbol::Dit, MorseSymbol::Dah) => MorseSymbol::Dit,
+            d if !self.long_dahs || d < cut(MorseSymbol::Dah, MorseSymbol::LongDah) => MorseSymbol::Dah,
+            d if d < cut(MorseSymbol::LongDah, MorseSymbol::LongerDah) => MorseSymbol::LongDah,
+            _ => MorseSymbol::LongerDah,
+        }
+    }
+
+    /// Whether a gap this long, followed by another mark, is a space inside
+    /// the character.
+    fn is_space(&self, unpressed: Duration) -> bool {
+        self.space.is_some_and(|space| unpressed > space)
+    }
+
+    fn ends_character(&self, unpressed: Duration) -> bool {
+        unpressed > self.letter
+    }
+
+    fn ends_word(&self, unpressed: Duration) -> bool {
+        unpressed > self.word
+    }
+}
+
+/// The duration `fraction` of the way from `short` to `long`.
+fn between(short: Duration, long: Duration, fraction: f64) -> Duration {
+    short + long.saturating_sub(short).mul_f64(fraction)
+}
+
+/// Estimates the length of a dit from the latest marks. Sorted by length,
+/// the marks fall into a cluster of dits and a cluster of dahs about three
+/// times as long, split where one mark is much longer than the one before.
+///
+/// Gaps are left out on purpose. Only the gaps inside a character are a
+/// unit long; letter and word gaps stretch with Farnsworth spacing and with
+/// every pause to think, and the estimate would follow them. Marks are
+/// always keyed at the character speed, and the gaps are then cut against
+/// the unit the marks give.
+#[derive(Debug, Clone, Default)]
+struct SpeedTracker {
+    marks: VecDeque<Duration>,
+}
+
+impl SpeedTracker {
+    /// Adds a mark and estimates the unit, starting from the current `unit`
+    /// when the marks so far are all of one kind. A mark well over the
+    /// `longest` symbol, in units, means the operator slowed down, and the
+    /// marks before it are forgotten.
+    fn track(&mut self, mark: Duration, unit: Duration, longest: u32) -> Duration {
+        if mark > unit * (longest + 2) {
+            self.marks.clear();
+        }
+        self.marks.push_back(mark);
+        if self.marks.len() > SPEED_WINDOW {
+            self.marks.pop_front();
+        }
+        let mut marks: Vec<f64> = self.marks.iter().map(Duration::as_secs_f64).collect();
+        marks.sort_by(f64::total_cmp);
+        let mean = |marks: &[f64]| marks.iter().sum::<f64>() / marks.len() as f64;
+        let ratio = |i: usize| marks[i] / marks[i - 1];
+        let split = (1..marks.len()).max_by(|a, b| ratio(*a).total_cmp(&ratio(*b)));
+        let unit = match split {
+            Some(i) if ratio(i) > 1.8 => mean(&marks[..i]),
+            // Only dits or only dahs: tell which from the current speed.
+            _ if mean(&marks) < 2.0 * unit.as_secs_f64() => mean(&marks),
+            _ => mean(&marks) / 3.0,
+        };
+        Duration::from_secs_f64(unit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Keys `text` as `timeline` lays it out from a fresh start, polling the
+    /// decoder during every gap like the decoder view does, and writes out
+    /// what was decoded.
+    fn key(
+        decoder: &mut KeyingDecoder,
+        text: &str,
+        timeline: impl Fn(&[crate::morse::Element]) -> Vec<(bool, Duration)>,
+    ) -> String {
+        decoder.reset();
+        let elements = decoder.code_books().current().encode_str(text).unwrap();
+        let poll = Duration::from_millis(5);
+        let mut decoded = vec![];
+        let mut now = Duration::ZERO;
+        for (on, duration) in timeline(&elements) {
+            if on {
+                decoded.extend(decoder.press(now));
+                now += duration;
+                decoded.extend(decoder.release(now));
+            } else {
+                let end = now + duration;
+                while now + poll < end {
+                    now += poll;
+                    decoded.extend(decoder.update(now));
+                }
+                now = end;
+            }
+        }
+        decoded.extend(decoder.update(now + Duration::from_secs(60)));
+        decoded
+            .into_iter()
+            .filter_map(|d| match d {
+                Decoded::Token(token) => Some(token.to_string()),
+                Decoded::Unknown(_) => Some("#".to_string()),
+                Decoded::WordGap => Some(" ".to_string()),
+                Decoded::Symbol(_) | Decoded::Shift(_) => None,
+            })
+            .collect()
+    }
+
+    fn decoder(book: &str, timing: Timing) -> KeyingDecoder {
+        let book = crate::morse::built_in().into_iter().find(|b| b.name() == book).unwrap();
+        let mut decoder = KeyingDecoder::new(CodeBooks::new(vec![book]), timing);
+        decoder.set_tracking(false);
+        decoder
+    }
+
+    #[test]
+    fn latin_round_trip() {
+        let timing = Timing::default();
+        let decoded = key(&mut decoder("Latin", timing), "cq de paris 73", |e| timing.timeline(e));
+        assert_eq!(decoded, "cq de paris 73 ");
+    }
+
+    #[test]
+    fn american_round_trip() {
+        let timing = Timing::default();
+        assert_eq!(key(&mut decoder("American", timing), "cat dog", |e| timing.timeline(e)), "cat dog ");
+    }
+
+    #[test]
+    fn tap_code_round_trip() {
+        let mut decoder = decoder("Tap code", Timing::default());
+        let taps = TapTiming::default();
+        assert_eq!(key(&mut decoder, "hi there", |e| taps.timeline(e)), "hi there ");
+    }
+
+    #[test]
+    fn tap_code_ignores_morse_speed() {
+        // Taps 150 ms apart with a 600 ms pause between the groups, at the
+        // fastest morse speed.
+        let mut decoder = decoder("Tap code", Timing::new(MAX_WPM));
+        let ms = Duration::from_millis;
+        let mut decoded = vec![];
+        for start in [0, 150, 800, 950, 1100] {
+            decoded.extend(decoder.press(ms(start)));
+            decoded.extend(decoder.release(ms(start + 50)));
+        }
+        decoded.extend(decoder.update(ms(3000)));
+        assert!(decoded.contains(&Decoded::Token(Token::Char('h'))), "{:?}", decoded);
+        assert!(!decoded.iter().any(|d| matches!(d, Decoded::Unknown(_))));
+    }
+
+    #[test]
+    fn farnsworth_round_trip() {
+        let timing = Timing::farnsworth(20.0, 10.0);
+        assert_eq!(key(&mut decoder("Latin", timing), "paris paris", |e| timing.timeline(e)), "paris paris ");
+    }
+
+    #[test]
+    fn do_and_sn_switch_to_wabun_and_back() {
+        let timing = Timing::default();
+        let mut decoder = decoder("Latin", timing);
+        decoder.reset();
+        let ms = Duration::from_millis;
+        let mut decoded = vec![];
+        let mut now = ms(0);
+        for (on, duration) in timing.timeline(&crate::morse::encode_str("<DO>").unwrap()) {
+            if on {
+                decoded.extend(decoder.press(now));
+                decoded.extend(decoder.release(now + duration));
+            }
+            now += duration;
+        }
+        decoded.extend(decoder.update(now + ms(1000)));
+        assert!(decoded.contains(&Decoded::Shift(Prosign::DO)), "{:?}", decoded);
+        assert_eq!(decoder.code_books().current().name(), "Wabun");
+        assert_eq!(key(&mut decoder, "イロハ", |e| timing.timeline(e)), "イロハ ");
+        key(&mut decoder, "<SN>", |e| timing.timeline(e));
+        assert_eq!(decoder.code_books().current().name(), "Latin");
+        assert_eq!(key(&mut decoder, "cq", |e| timing.timeline(e)), "cq ");
+    }
+
+    #[test]
+    fn error_prosign() {
+        let timing = Timing::default();
+        let mut decoder = decoder("Latin", timing);
+        assert_eq!(key(&mut decoder, "<HH>", |e| timing.timeline(e)), Prosign::Error.to_string() + " ");
+    }
+
+    #[test]
+    fn prosigns_first() {
+        let timing = Timing::default();
+        let mut decoder = decoder("Latin", timing);
+        assert_eq!(key(&mut decoder, "+", |e| timing.timeline(e)), "+ ");
+        decoder.set_prosigns_first(true);
+        assert_eq!(key(&mut decoder, "+", |e| timing.timeline(e)), Prosign::AR.to_string() + " ");
+    }
+
+    #[test]
+    fn reset_drops_the_character_in_progress() {
+        let mut decoder = decoder("Latin", Timing::new(20.0));
+        let ms = Duration::from_millis;
+        decoder.press(ms(5000));
+        decoder.reset();
+        assert!(!decoder.is_pressed());
+        assert_eq!(decoder.release(ms(60)), None);
+        decoder.press(ms(0));
+        decoder.release(ms(180));
+        decoder.reset();
+        assert!(decoder.partial().is_empty());
+        assert_eq!(decoder.update(ms(10_000)), vec![]);
+    }
+
+    #[test]
+    fn follows_speed_changes() {
+        let mut decoder = decoder("Latin", Timing::new(20.0));
+        decoder.set_tracking(true);
+        assert_eq!(key(&mut decoder, "paris paris", |e| Timing::new(12.0).timeline(e)), "paris paris ");
+        assert!((decoder.timing().character_wpm() - 12.0).abs() < 0.5);
+        assert_eq!(key(&mut decoder, "paris paris", |e| Timing::new(25.0).timeline(e)), "paris paris ");
+        assert!((decoder.timing().character_wpm() - 25.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn speed_stays_put_without_tracking() {
+        let mut decoder = decoder("Latin", Timing::new(20.0));
+        key(&mut decoder, "paris", |e| Timing::new(12.0).timeline(e));
+        assert_eq!(decoder.timing().character_wpm(), 20.0);
+    }
+
+    #[test]
+    fn speed_tracker() {
+        let ms = Duration::from_millis;
+        let mut tracker = SpeedTracker::default();
+        // Dits alone are told from dahs by the current unit.
+        assert_eq!(tracker.track(ms(100), ms(60), 3), ms(100));
+        assert_eq!(tracker.track(ms(300), ms(100), 3), ms(100));
+        assert_eq!(tracker.track(ms(90), ms(100), 3), ms(95));
+        // A much longer mark starts over.
+        let mut tracker = SpeedTracker::default();
+        assert_eq!(tracker.track(ms(180), ms(60), 3), ms(60));
+        let unit = tracker.track(ms(600), ms(60), 3);
+        assert!((unit.as_secs_f64() - 0.2).abs() < 1e-6, "{:?}", unit);
+    }
+
+    #[test]
+    fn word_gap_comes_once_after_the_character() {
+        let mut decoder = decoder("Latin", Timing::new(20.0));
+        let ms = Duration::from_millis;
+        assert_eq!(decoder.press(ms(0)), vec![]);
+        assert_eq!(decoder.release(ms(60)), Some(Decoded::Symbol(MorseSymbol::Dit)));
+        assert_eq!(decoder.update(ms(150)), vec![]);
+        assert_eq!(decoder.update(ms(250)), vec![Decoded::Token(Token::Char('e'))]);
+        assert_eq!(decoder.update(ms(350)), vec![]);
+        assert_eq!(decoder.update(ms(500)), vec![Decoded::WordGap]);
+        assert_eq!(decoder.update(ms(5000)), vec![]);
+    }
+
+    #[test]
+    fn press_after_a_long_gap_finishes_the_word() {
+        let mut decoder = decoder("Latin", Timing::new(20.0));
+        let ms = Duration::from_millis;
+        decoder.press(ms(0));
+        decoder.release(ms(60));
+        assert_eq!(
+            decoder.press(ms(1000)),
+            vec![Decoded::Token(Token::Char('e')), Decoded::WordGap]
+        );
+    }
+
+    #[test]
+    fn updates_in_stamp_order_keep_the_character() {
+        // An update stamped just before the second press, as a tick sent
+        // then is, must not end the character.
+        let ms = Duration::from_millis;
+        let mut decoder = decoder("Latin", Timing::new(20.0));
+        let mut decoded = decoder.press(ms(0));
+        decoded.extend(decoder.release(ms(60)));
+        decoded.extend(decoder.update(ms(165)));
+        decoded.extend(decoder.press(ms(170)));
+        decoded.extend(decoder.release(ms(230)));
+        decoded.extend(decoder.update(ms(2000)));
+        assert!(decoded.contains(&Decoded::Token(Token::Char('i'))), "{:?}", decoded);
+    }
+}
@@ -1,7 +1,17 @@
-mod morse;
+pub mod morse;
+pub mod code_book;
 mod ring;
+mod bidi;
+mod trie;
 pub mod menu;
 pub mod code_table;
 pub mod tui;
 pub mod app;
 pub mod decoder;
+pub mod keying;
+pub mod encoder;
+pub mod notation;
+pub mod timing;
+pub mod abbreviations;
+pub mod cut_numbers;
+pub mod tap_code;
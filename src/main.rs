@@ -1,8 +1,27 @@
-use std::io:: Result;
+use std::io::{Error, ErrorKind, Result};
+use std::sync::Arc;
+
+use mo::code_book::{CodeBook, CodeBooks, LoadError};
 
 fn main() -> Result<()> {
+    // Code book files given as arguments come before the built-in alphabets.
+    let mut books = std::env::args()
+        .skip(1)
+        .map(|path| {
+            CodeBook::load(&path)
+                .map(Arc::new)
+                .map_err(|error| {
+                    let kind = match &error {
+                        LoadError::Io(error) => error.kind(),
+                        _ => ErrorKind::InvalidData,
+                    };
+                    Error::new(kind, format!("{}: {}", path, error))
+                })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    books.extend(mo::morse::built_in());
     let mut terminal = mo::tui::init()?;
-    let app_result = mo::app::App::default().run(&mut terminal);
+    let app_result = mo::app::App::with_code_books(CodeBooks::new(books)).run(&mut terminal);
     mo::tui::restore()?;
     app_result
 }
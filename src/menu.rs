@@ -25,6 +25,7 @@ macro_rules! choice {
 
 choice!(
     DecodeMode; "Decode",
+    EncodeMode; "Encode",
     CodeTable; "Morse Code Table",
     Shutdown; "Exit"
 );
@@ -32,7 +33,8 @@ choice!(
 impl Choice {
     fn next(&mut self) {
         match self {
-            Self::DecodeMode => *self = Self::CodeTable,
+            Self::DecodeMode => *self = Self::EncodeMode,
+            Self::EncodeMode => *self = Self::CodeTable,
             Self::CodeTable => *self = Self::Shutdown,
             Self::Shutdown => {}
         }
@@ -41,7 +43,8 @@ impl Choice {
     fn prev(&mut self) {
         match self {
             Self::DecodeMode => {}
-            Self::CodeTable => *self = Self::DecodeMode,
+            Self::EncodeMode => *self = Self::DecodeMode,
+            Self::CodeTable => *self = Self::EncodeMode,
             Self::Shutdown => *self = Self::CodeTable,
         }
     }
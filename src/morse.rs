@@ -1,88 +1,772 @@
-#[derive(Debug)]
+//! The morse codec: symbols, tokens and the built-in code books.
+//!
+//! The free functions use the ITU Latin alphabet; [`CodeBook`] does the same
+//! for any other alphabet, including ones loaded from a file.
+//!
+//! ```
+//! use mo::morse::{self, MorseSymbol, Token};
+//!
+//! let a = morse::encode_character('a')?;
+//! assert_eq!(a.symbols(), [MorseSymbol::Dit, MorseSymbol::Dah]);
+//! assert_eq!(morse::decode_symbols(a.symbols())?, Token::Char('a'));
+//!
+//! let elements = morse::encode_str("cq de")?;
+//! assert_eq!(morse::decode_elements(&elements)?, "cq de");
+//! # Ok::<(), morse::Error>(())
+//! ```
+
+use std::sync::{Arc, OnceLock};
+
+use ratatui::style::Style;
+use ratatui::text::Span;
+
+use crate::code_book::CodeBook;
+
+/// Errors returned by the codec.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The token has no code in the code book.
+    UnknownToken(Token),
+    /// The symbols are not the code of anything in the code book.
+    UnknownSequence(Vec<MorseSymbol>),
+    /// The character is not a glyph of any known notation.
+    UnknownGlyph(char),
+    /// A line of a code book could not be read.
+    Parse { line: usize, reason: String },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::UnknownToken(token) => write!(f, "{} has no morse code", token),
+            Error::UnknownSequence(symbols) => {
+                let notation = crate::notation::Notation::with_glyphs(crate::notation::Glyphs::Ascii);
+                write!(f, "{} is not a morse code", notation.format_symbols(symbols))
+            }
+            Error::UnknownGlyph(c) => write!(f, "'{}' is not a morse symbol", c),
+            Error::Parse { line, reason } => write!(f, "line {}: {}", line, reason),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Encodes a character with the ITU Latin alphabet, regardless of case.
+pub fn encode_character(character: char) -> Result<EncodedChar> {
+    latin().encode_character(character)
+}
+
+/// Decodes the symbols of one character with the ITU Latin alphabet.
+pub fn decode_symbols(symbols: &[MorseSymbol]) -> Result<Token> {
+    latin().decode_symbols(symbols)
+}
+
+/// Decodes the symbols of one character with the ITU Latin alphabet, reading
+/// AR, BT and KN as prosigns. See [`CodeBook::decode_symbols_prosigns_first`].
+pub fn decode_symbols_prosigns_first(symbols: &[MorseSymbol]) -> Result<Token> {
+    latin().decode_symbols_prosigns_first(symbols)
+}
+
+/// Encodes a message with the ITU Latin alphabet. See [`CodeBook::encode_str`].
+pub fn encode_str(text: &str) -> Result<Vec<Element>> {
+    latin().encode_str(text)
+}
+
+/// Encodes a message with the ITU Latin alphabet, transliterating characters
+/// it has no code for. See [`CodeBook::encode_str_transliterated`].
+pub fn encode_str_transliterated(text: &str) -> Result<Vec<Element>> {
+    latin().encode_str_transliterated(text)
+}
+
+/// Decodes a message with the ITU Latin alphabet. See [`CodeBook::decode_elements`].
+pub fn decode_elements(elements: &[Element]) -> Result<String> {
+    latin().decode_elements(elements)
+}
+
+/// Decodes a message with the ITU Latin alphabet, prosigns first. See
+/// [`CodeBook::decode_elements_prosigns_first`].
+pub fn decode_elements_prosigns_first(elements: &[Element]) -> Result<String> {
+    latin().decode_elements_prosigns_first(elements)
+}
+
+/// The elementary signals of morse.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum MorseSymbol {
     Dit,
     Dah,
+    /// The long dash of American Morse, used for L.
+    LongDah,
+    /// The even longer dash of American Morse, used for 0.
+    LongerDah,
+    /// A space inside a character, as in the American Morse C (`.. .`).
+    Space,
 }
 
-pub struct EndodedChar(Vec<MorseSymbol>);
+impl MorseSymbol {
+    pub const ALL: [MorseSymbol; 5] = [
+        MorseSymbol::Dit,
+        MorseSymbol::Dah,
+        MorseSymbol::LongDah,
+        MorseSymbol::LongerDah,
+        MorseSymbol::Space,
+    ];
+}
 
-impl std::fmt::Display for EndodedChar {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        for symbol in self.0.iter() {
-            let c = match symbol {
-                MorseSymbol::Dit => "•",
-                MorseSymbol::Dah => "—",
+/// One element of a keyed message: a symbol, or one of the gaps that
+/// separate letters and words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Element {
+    Symbol(MorseSymbol),
+    LetterGap,
+    WordGap,
+}
+
+/// The code of a single character or prosign.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodedChar(Vec<MorseSymbol>);
+
+impl EncodedChar {
+    pub fn symbols(&self) -> &[MorseSymbol] {
+        &self.0
+    }
+
+    /// The symbols as styled spans, separated by spaces and padded with
+    /// spaces up to `width` columns. Long dashes take the style of dahs.
+    pub fn spans(&self, width: usize, dit: Style, dah: Style) -> Vec<Span<'static>> {
+        let mut spans = vec![];
+        for (i, symbol) in self.0.iter().enumerate() {
+            if i > 0 {
+                spans.push(Span::raw(" "));
+            }
+            let style = match symbol {
+                MorseSymbol::Dit => dit,
+                _ => dah,
             };
-            write!(f, "{} ", c)?; 
+            spans.push(Span::styled(symbol_glyph(*symbol), style));
+        }
+        let used: usize = spans.iter().map(|span| span.content.chars().count()).sum();
+        if used < width {
+            spans.push(Span::raw(" ".repeat(width - used)));
+        }
+        spans
+    }
+}
+
+impl From<Vec<MorseSymbol>> for EncodedChar {
+    fn from(symbols: Vec<MorseSymbol>) -> Self {
+        Self(symbols)
+    }
+}
+
+/// Writes the symbols separated by spaces. A width pads the result, so
+/// `{:12}` lines codes of any length up in a column.
+impl std::fmt::Display for EncodedChar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = self.0.iter().map(|s| symbol_glyph(*s)).collect::<Vec<_>>().join(" ");
+        f.pad(&text)
+    }
+}
+
+fn symbol_glyph(symbol: MorseSymbol) -> &'static str {
+    match symbol {
+        MorseSymbol::Dit => "•",
+        MorseSymbol::Dah => "—",
+        MorseSymbol::LongDah => "⸺",
+        MorseSymbol::LongerDah => "⸻",
+        MorseSymbol::Space => " ",
+    }
+}
+
+/// The case decoded characters are written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Case {
+    #[default]
+    Lower,
+    Upper,
+}
+
+impl Case {
+    /// Writes out a token; prosigns keep their usual capitals.
+    pub fn apply(&self, token: Token) -> String {
+        match (self, token) {
+            (Case::Lower, Token::Char(c)) => c.to_lowercase().collect(),
+            (Case::Upper, Token::Char(c)) => c.to_uppercase().collect(),
+            (Case::Lower, Token::Digraph(a, b)) => a.to_lowercase().chain(b.to_lowercase()).collect(),
+            (Case::Upper, Token::Digraph(a, b)) => a.to_uppercase().chain(b.to_uppercase()).collect(),
+            (_, Token::Prosign(p)) => p.to_string(),
         }
-        for _ in 0..(5-self.0.len()) {
-            write!(f, "  ")?; 
+    }
+
+    pub fn toggle(self) -> Self {
+        match self {
+            Case::Lower => Case::Upper,
+            Case::Upper => Case::Lower,
         }
-        Ok(())
-    }
-}
-
-pub fn decode_symbols(symbols: &Vec<MorseSymbol>) -> Option<char> {
-    let res = match symbols.as_slice() {
-        [MorseSymbol::Dit, MorseSymbol::Dah] => Some('a'),
-        [MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dit] => Some('b'),
-        [MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dit] => Some('c'),
-        [MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dit] => Some('d'),
-        [MorseSymbol::Dit] => Some('e'),
-        [MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dit] => Some('f'),
-        [MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dit] => Some('g'),
-        [MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dit] => Some('h'),
-        [MorseSymbol::Dit, MorseSymbol::Dit] => Some('i'),
-        [MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dah] => Some('j'),
-        [MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dah] => Some('k'),
-        [MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dit] => Some('l'),
-        [MorseSymbol::Dah, MorseSymbol::Dah] => Some('m'),
-        [MorseSymbol::Dah, MorseSymbol::Dit] => Some('n'),
-        [MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dah] => Some('o'),
-        [MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dit] => Some('p'),
-        [MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dah] => Some('q'),
-        [MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dit] => Some('r'),
-        [MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dit] => Some('s'),
-        [MorseSymbol::Dah] => Some('t'),
-        [MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dah] => Some('u'),
-        [MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dah] => Some('v'),
-        [MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dah] => Some('w'),
-        [MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dah] => Some('x'),
-        [MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dah] => Some('y'),
-        [MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dit] => Some('z'),
-        _ => None,
+    }
+}
+
+/// Plain Latin spellings of accented and other extended letters, for code
+/// books that have no code for them.
+const TRANSLITERATIONS: &[(char, &str)] = &[
+    ('à', "a"), ('á', "a"), ('â', "a"), ('ã', "a"), ('ä', "ae"), ('å', "a"), ('ā', "a"), ('ą', "a"),
+    ('æ', "ae"), ('ç', "c"), ('ć', "c"), ('č', "c"), ('ď', "d"), ('đ', "d"), ('ð', "d"),
+    ('è', "e"), ('é', "e"), ('ê', "e"), ('ë', "e"), ('ē', "e"), ('ę', "e"), ('ě', "e"),
+    ('ğ', "g"), ('ĥ', "ch"), ('ì', "i"), ('í', "i"), ('î', "i"), ('ï', "i"), ('ı', "i"),
+    ('ł', "l"), ('ľ', "l"), ('ñ', "n"), ('ń', "n"), ('ň', "n"),
+    ('ò', "o"), ('ó', "o"), ('ô', "o"), ('õ', "o"), ('ö', "oe"), ('ő', "o"), ('ø', "o"), ('œ', "oe"),
+    ('ř', "r"), ('ß', "ss"), ('ś', "s"), ('š', "s"), ('ş', "s"), ('ť', "t"), ('ţ', "t"), ('þ', "th"),
+    ('ù', "u"), ('ú', "u"), ('û', "u"), ('ü', "ue"), ('ű', "u"), ('ů', "u"),
+    ('ý', "y"), ('ÿ', "y"), ('ź', "z"), ('ż', "z"), ('ž', "z"),
+];
+
+/// The plain Latin spelling of an extended letter, in lowercase, as `ss`
+/// for `ß` or `o` for `Ø`.
+pub fn transliterate(character: char) -> Option<&'static str> {
+    let mut lower = character.to_lowercase();
+    let character = match (lower.next(), lower.next()) {
+        (Some(l), None) => l,
+        _ => character,
     };
-    res
-}
-
-pub fn encode_character(character: char) -> Option<EndodedChar> {
-    Some(EndodedChar(match character {
-        'a' => vec![MorseSymbol::Dit, MorseSymbol::Dah],
-        'b' => vec![MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dit],
-        'c' => vec![MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dit],
-        'd' => vec![MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dit],
-        'e' => vec![MorseSymbol::Dit],
-        'f' => vec![MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dit],
-        'g' => vec![MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dit],
-        'h' => vec![MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dit],
-        'i' => vec![MorseSymbol::Dit, MorseSymbol::Dit],
-        'j' => vec![MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dah],
-        'k' => vec![MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dah],
-        'l' => vec![MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dit],
-        'm' => vec![MorseSymbol::Dah, MorseSymbol::Dah],
-        'n' => vec![MorseSymbol::Dah, MorseSymbol::Dit],
-        'o' => vec![MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dah],
-        'p' => vec![MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dit],
-        'q' => vec![MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dah],
-        'r' => vec![MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dit],
-        's' => vec![MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dit],
-        't' => vec![MorseSymbol::Dah],
-        'u' => vec![MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dah],
-        'v' => vec![MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dah],
-        'w' => vec![MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dah],
-        'x' => vec![MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dit, MorseSymbol::Dah],
-        'y' => vec![MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dah],
-        'z' => vec![MorseSymbol::Dah, MorseSymbol::Dah, MorseSymbol::Dit, MorseSymbol::Dit],
-        _ => return None,
-    }))
+    TRANSLITERATIONS.iter().find(|(c, _)| *c == character).map(|(_, plain)| *plain)
+}
+
+/// Procedural signals, sent as a single run of symbols without letter gaps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Prosign {
+    AR,
+    SK,
+    BT,
+    KN,
+    Sos,
+    Error,
+    /// Switches to Wabun kana.
+    DO,
+    /// Understood; also switches from Wabun back to the previous alphabet.
+    SN,
+}
+
+impl Prosign {
+    const ALL: [Prosign; 8] = [
+        Prosign::AR,
+        Prosign::SK,
+        Prosign::BT,
+        Prosign::KN,
+        Prosign::Sos,
+        Prosign::Error,
+        Prosign::DO,
+        Prosign::SN,
+    ];
+
+    /// The letters the prosign is written with, as in `AR`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Prosign::AR => "AR",
+            Prosign::SK => "SK",
+            Prosign::BT => "BT",
+            Prosign::KN => "KN",
+            Prosign::Sos => "SOS",
+            Prosign::Error => "HH",
+            Prosign::DO => "DO",
+            Prosign::SN => "SN",
+        }
+    }
+
+    /// Looks a prosign up by its letters, ignoring case.
+    pub fn from_name(name: &str) -> Option<Prosign> {
+        Self::ALL.into_iter().find(|p| p.name().eq_ignore_ascii_case(name))
+    }
+}
+
+impl std::fmt::Display for Prosign {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<{}>", self.name())
+    }
+}
+
+/// A single decoded unit: a character, two letters sent as one code like
+/// the CH of German and Czech, or a prosign.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Token {
+    Char(char),
+    Digraph(char, char),
+    Prosign(Prosign),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Char(c) => write!(f, "{}", c),
+            Token::Digraph(a, b) => write!(f, "{}{}", a, b),
+            Token::Prosign(p) => write!(f, "{}", p),
+        }
+    }
+}
+
+/// The ITU-R M.1677 letters, in the format read by [`CodeBook::parse`].
+const LATIN: &str = "\
+a .-
+b -...
+c -.-.
+d -..
+e .
+f ..-.
+g --.
+h ....
+i ..
+j .---
+k -.-
+l .-..
+m --
+n -.
+o ---
+p .--.
+q --.-
+r .-.
+s ...
+t -
+u ..-
+v ...-
+w .--
+x -..-
+y -.--
+z --..
+";
+
+/// The letters of other languages written in Latin script. CH is a digraph:
+/// `----` decodes to it, and it is sent for `ĥ` by the transliterating
+/// encoder, while a plain `ch` is still sent as C and H.
+const LATIN_EXTENDED: &str = "\
+à .--.-
+ä .-.-
+ch ----
+é ..-..
+ñ --.--
+ö ---.
+ü ..--
+";
+
+/// The Russian letters. `ё` is sent as `е`, and `ъ` as `ь`.
+const CYRILLIC: &str = "\
+а .-
+б -...
+в .--
+г --.
+д -..
+е .
+ё .
+ж ...-
+з --..
+и ..
+й .---
+к -.-
+л .-..
+м --
+н -.
+о ---
+п .--.
+р .-.
+с ...
+т -
+у ..-
+ф ..-.
+х ....
+ц -.-.
+ч ---.
+ш ----
+щ --.-
+ь -..-
+ъ -..-
+ы -.--
+э ..-..
+ю ..--
+я .-.-
+";
+
+/// The Greek letters. Accented vowels and the final `ς` are sent as their
+/// plain forms.
+const GREEK: &str = "\
+α .-
+ά .-
+β -...
+γ --.
+δ -..
+ε .
+έ .
+ζ --..
+η ....
+ή ....
+θ -.-.
+ι ..
+ί ..
+κ -.-
+λ .-..
+μ --
+ν -.
+ξ -..-
+ο ---
+ό ---
+π .--.
+ρ .-.
+σ ...
+ς ...
+τ -
+υ -.--
+ύ -.--
+φ ..-.
+χ ----
+ψ --.-
+ω .--
+ώ .--
+";
+
+/// The Korean jamo as assigned by SKATS, each sharing the code of a Latin
+/// letter. Syllables are sent one jamo at a time.
+const KOREAN: &str = "\
+ㄱ .-..
+ㄴ ..-.
+ㄷ -...
+ㄹ ...-
+ㅁ --
+ㅂ .--
+ㅅ --.
+ㅇ -.-
+ㅈ .--.
+ㅊ -.-.
+ㅋ -..-
+ㅌ --..
+ㅍ ---
+ㅎ .---
+ㅏ .
+ㅑ ..
+ㅓ -
+ㅕ ...
+ㅗ .-
+ㅛ -.
+ㅜ ....
+ㅠ .-.
+ㅡ -..
+ㅣ ..-
+ㅐ --.-
+ㅔ -.--
+";
+
+/// The Hebrew letters. Final forms are sent as their regular forms.
+const HEBREW: &str = "\
+א .-
+ב -...
+ג --.
+ד -..
+ה ---
+ו .
+ז --..
+ח ....
+ט ..-
+י ..
+כ -.-
+ך -.-
+ל .-..
+מ --
+ם --
+נ -.
+ן -.
+ס -.-.
+ע .---
+פ .--.
+ף .--.
+צ .--
+ץ .--
+ק --.-
+ר .-.
+ש ...
+ת -
+";
+
+/// The Arabic letters.
+const ARABIC: &str = "\
+ا .-
+ب -...
+ت -
+ث -.-.
+ج .---
+ح ....
+خ ---
+د -..
+ذ --..
+ر .-.
+ز ---.
+س ...
+ش ----
+ص -..-
+ض ...-
+ط ..-
+ظ -.--
+ع .-.-
+غ --.
+ف ..-.
+ق --.-
+ك -.-
+ل .-..
+م --
+ن -.
+ه ..-..
+و .--
+ي ..
+ء .
+";
+
+/// American (railroad) Morse, complete with its own digits and punctuation.
+const AMERICAN: &str = "\
+a .-
+b -...
+c .. .
+d -..
+e .
+f .-.
+g --.
+h ....
+i ..
+j -.-.
+k -.-
+l _
+m --
+n -.
+o . .
+p .....
+q ..-.
+r . ..
+s ...
+t -
+u ..-
+v ...-
+w .--
+x .-..
+y .. ..
+z ... .
+& . ...
+1 .--.
+2 ..-..
+3 ...-.
+4 ....-
+5 ---
+6 ......
+7 --..
+8 -....
+9 -..-
+0 =
+. ..--..
+, .-.-
+? -..-.
+! ---.
+";
+
+/// Wabun kana, entered from another alphabet with the DO prosign and left
+/// with SN.
+const WABUN: &str = "\
+ア --.--
+イ .-
+ウ ..-
+エ -.---
+オ .-...
+カ .-..
+キ -.-..
+ク ...-
+ケ -.--
+コ ----
+サ -.-.-
+シ --.-.
+ス ---.-
+セ .---.
+ソ ---.
+タ -.
+チ ..-.
+ツ .--.
+テ .-.--
+ト ..-..
+ナ .-.
+ニ -.-.
+ヌ ....
+ネ --.-
+ノ ..--
+ハ -...
+ヒ --..-
+フ --..
+ヘ .
+ホ -..
+マ -..-
+ミ ..-.-
+ム -
+メ -...-
+モ -..-.
+ヤ .--
+ユ -..--
+ヨ --
+ラ ...
+リ --.
+ル -.--.
+レ ---
+ロ .-.-
+ワ -.-
+ヰ .-..-
+ヱ .--..
+ヲ .---
+ン .-.-.
+゛ ..
+゜ ..--.
+ー .--.-
+、 .-.-.-
+」 .-.-..
+（ -.--.-
+） .-..-.
+<SN> ...-.
+";
+
+/// Digits from ITU-R M.1677, shared by every built-in alphabet.
+const DIGITS: &str = "\
+0 -----
+1 .----
+2 ..---
+3 ...--
+4 ....-
+5 .....
+6 -....
+7 --...
+8 ---..
+9 ----.
+";
+
+/// Punctuation and prosigns from ITU-R M.1677, shared by every built-in
+/// alphabet but Wabun, whose kana reuse these codes.
+const SIGNS: &str = "\
+. .-.-.-
+, --..--
+? ..--..
+' .----.
+! -.-.--
+/ -..-.
+( -.--.
+) -.--.-
+& .-...
+: ---...
+; -.-.-.
+= -...-
++ .-.-.
+- -....-
+_ ..--.-
+\" .-..-.
+$ ...-..-
+@ .--.-.
+<AR> .-.-.
+<SK> ...-.-
+<BT> -...-
+<KN> -.--.
+<SOS> ...---...
+<HH> ........
+<SN> ...-.
+<DO> -..---
+";
+
+/// The built-in alphabets, each completed with the shared digits,
+/// punctuation and prosigns, followed by American Morse, Wabun and the tap
+/// code.
+pub fn built_in() -> Vec<Arc<CodeBook>> {
+    static BUILT_IN: OnceLock<Vec<Arc<CodeBook>>> = OnceLock::new();
+    BUILT_IN
+        .get_or_init(|| {
+            let others = [
+                ("Russian", CYRILLIC),
+                ("Greek", GREEK),
+                ("Korean (SKATS)", KOREAN),
+                ("Hebrew", HEBREW),
+                ("Arabic", ARABIC),
+            ];
+            std::iter::once(latin())
+                .chain(others.into_iter().map(|(name, letters)| alphabet(name, &[letters])))
+                .chain([american(), wabun(), crate::tap_code::code_book()])
+                .collect()
+        })
+        .clone()
+}
+
+/// An alphabet completed with the shared digits, punctuation and prosigns.
+fn alphabet(name: &str, letters: &[&str]) -> Arc<CodeBook> {
+    let book = CodeBook::parse(name, &[letters, &[DIGITS, SIGNS]].concat().concat());
+    Arc::new(book.expect("built-in code book is valid"))
+}
+
+/// The Latin alphabet that the free functions use.
+fn latin() -> Arc<CodeBook> {
+    static LATIN_BOOK: OnceLock<Arc<CodeBook>> = OnceLock::new();
+    LATIN_BOOK
+        .get_or_init(|| alphabet("Latin", &[LATIN, LATIN_EXTENDED]))
+        .clone()
+}
+
+fn american() -> Arc<CodeBook> {
+    Arc::new(CodeBook::parse("American", AMERICAN).expect("built-in code book is valid"))
+}
+
+/// The Wabun kana code book that the DO prosign switches to.
+pub fn wabun() -> Arc<CodeBook> {
+    static WABUN_BOOK: OnceLock<Arc<CodeBook>> = OnceLock::new();
+    WABUN_BOOK
+        .get_or_init(|| {
+            let book = CodeBook::parse("Wabun", &[WABUN, DIGITS].concat());
+            Arc::new(book.expect("built-in code book is valid"))
+        })
+        .clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::{Glyphs, Notation};
+
+    #[test]
+    fn characters_first_by_default() {
+        let elements = encode_str("1+1=2").unwrap();
+        assert_eq!(decode_elements(&elements).unwrap(), "1+1=2");
+        let plus = encode_character('+').unwrap();
+        assert_eq!(decode_symbols(plus.symbols()).unwrap(), Token::Char('+'));
+    }
+
+    #[test]
+    fn prosigns_survive_a_round_trip_prosigns_first() {
+        let elements = encode_str("cq <AR> <BT> <KN> <SK>").unwrap();
+        assert_eq!(decode_elements_prosigns_first(&elements).unwrap(), "cq <AR> <BT> <KN> <SK>");
+        let plus = encode_character('+').unwrap();
+        assert_eq!(decode_symbols_prosigns_first(plus.symbols()).unwrap(), Token::Prosign(Prosign::AR));
+    }
+
+    #[test]
+    fn transliteration() {
+        assert_eq!(transliterate('ß'), Some("ss"));
+        assert_eq!(transliterate('Ø'), Some("o"));
+        assert_eq!(transliterate('a'), None);
+        let elements = encode_str_transliterated("Straße").unwrap();
+        assert_eq!(decode_elements(&elements).unwrap(), "strasse");
+        assert!(encode_str("Straße").is_err());
+    }
+
+    #[test]
+    fn ch_is_sent_as_c_and_h() {
+        let ascii = Notation::with_glyphs(Glyphs::Ascii);
+        assert_eq!(ascii.format(&encode_str("which").unwrap()), ".-- .... .. -.-. ....");
+        assert_eq!(ascii.format(&encode_str("Schema").unwrap()), "... -.-. .... . -- .-");
+    }
+
+    #[test]
+    fn ch_digraph() {
+        let ch = decode_symbols(&[MorseSymbol::Dah; 4]).unwrap();
+        assert_eq!(ch, Token::Digraph('c', 'h'));
+        assert_eq!(Case::Upper.apply(ch), "CH");
+        let elements = encode_str_transliterated("ĥaoso").unwrap();
+        assert_eq!(Notation::with_glyphs(Glyphs::Ascii).format(&elements), "---- .- --- ... ---");
+        assert_eq!(decode_elements(&elements).unwrap(), "chaoso");
+    }
 }
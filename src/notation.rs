@@ -0,0 +1,200 @@
+use crate::morse::{Element, Error, MorseSymbol, Result};
+
+/// The ways a symbol can be written out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Glyphs {
+    /// `.-`, with `_` and `=` for the long dashes of American Morse.
+    Ascii,
+    /// `•—`, with `⸺` and `⸻` for the long dashes of American Morse.
+    #[default]
+    Unicode,
+    /// `di-dah`, the way morse is spoken when learning it by ear.
+    Spoken,
+    /// `10111`, one digit per unit of time: `1` while keyed, `0` in between.
+    /// Letter and word gaps are always three and seven units.
+    Binary,
+}
+
+impl Glyphs {
+    pub const ALL: [Glyphs; 4] = [Glyphs::Ascii, Glyphs::Unicode, Glyphs::Spoken, Glyphs::Binary];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Glyphs::Ascii => "ASCII",
+            Glyphs::Unicode => "Unicode",
+            Glyphs::Spoken => "spoken",
+            Glyphs::Binary => "binary",
+        }
+    }
+
+    /// The glyph set after this one, wrapping around after the last one.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|g| *g == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Textual morse, like `.- -... / -.-.`: the glyphs used for symbols and the
+/// separators placed between letters and between words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notation {
+    pub glyphs: Glyphs,
+    pub letter_separator: String,
+    pub word_separator: String,
+}
+
+impl Default for Notation {
+    fn default() -> Self {
+        Self {
+            glyphs: Glyphs::default(),
+            letter_separator: " ".to_string(),
+            word_separator: " / ".to_string(),
+        }
+    }
+}
+
+impl Notation {
+    pub fn with_glyphs(glyphs: Glyphs) -> Self {
+        Self {
+            glyphs,
+            ..Default::default()
+        }
+    }
+
+    /// Writes out the symbols of a single character.
+    pub fn format_symbols(&self, symbols: &[MorseSymbol]) -> String {
+        match self.glyphs {
+            Glyphs::Ascii | Glyphs::Unicode => symbols.iter().map(|s| self.glyph(*s)).collect(),
+            Glyphs::Spoken => symbols
+                .split(|s| *s == MorseSymbol::Space)
+                .map(|run| {
+                    let mut syllables: Vec<&str> = run.iter().map(|s| self.glyph(*s)).collect();
+                    // A dit is clipped to "di" unless it ends the run.
+                    if let Some(last) = syllables.last_mut() {
+                        if *last == "di" {
+                            *last = "dit";
+                        }
+                    }
+                    syllables.join("-")
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+            Glyphs::Binary => symbols
+                .iter()
+                .map(|s| self.glyph(*s))
+                .collect::<Vec<_>>()
+                .join("0"),
+        }
+    }
+
+    /// Writes out a whole message.
+    pub fn format(&self, elements: &[Element]) -> String {
+        let (letter_separator, word_separator) = match self.glyphs {
+            Glyphs::Binary => ("000", "0000000"),
+            _ => (self.letter_separator.as_str(), self.word_separator.as_str()),
+        };
+        let mut text = String::new();
+        let mut symbols = vec![];
+        for element in elements {
+            match element {
+                Element::Symbol(symbol) => symbols.push(*symbol),
+                Element::LetterGap => {
+                    text.push_str(&self.format_symbols(&symbols));
+                    text.push_str(letter_separator);
+                    symbols.clear();
+                }
+                Element::WordGap => {
+                    text.push_str(&self.format_symbols(&symbols));
+                    text.push_str(word_separator);
+                    symbols.clear();
+                }
+            }
+        }
+        text.push_str(&self.format_symbols(&symbols));
+        text
+    }
+
+    /// Reads dot/dash text written with either ASCII or Unicode glyphs.
+    /// The spaces of American Morse can only be read when the letter
+    /// separator is not a single space.
+    pub fn parse(&self, text: &str) -> Result<Vec<Element>> {
+        let mut elements = vec![];
+        for (w, word) in text.trim().split(self.word_separator.as_str()).enumerate() {
+            if w > 0 {
+                elements.push(Element::WordGap);
+            }
+            let letters = word
+                .split(self.letter_separator.as_str())
+                .map(str::trim)
+                .filter(|letter| !letter.is_empty());
+            for (l, letter) in letters.enumerate() {
+                if l > 0 {
+                    elements.push(Element::LetterGap);
+                }
+                for c in letter.chars() {
+                    let symbol = parse_glyph(c).ok_or(Error::UnknownGlyph(c))?;
+                    elements.push(Element::Symbol(symbol));
+                }
+            }
+        }
+        Ok(elements)
+    }
+
+    fn glyph(&self, symbol: MorseSymbol) -> &'static str {
+        match (self.glyphs, symbol) {
+            (Glyphs::Ascii, MorseSymbol::Dit) => ".",
+            (Glyphs::Ascii, MorseSymbol::Dah) => "-",
+            (Glyphs::Ascii, MorseSymbol::LongDah) => "_",
+            (Glyphs::Ascii, MorseSymbol::LongerDah) => "=",
+            (Glyphs::Unicode, MorseSymbol::Dit) => "•",
+            (Glyphs::Unicode, MorseSymbol::Dah) => "—",
+            (Glyphs::Unicode, MorseSymbol::LongDah) => "⸺",
+            (Glyphs::Unicode, MorseSymbol::LongerDah) => "⸻",
+            (Glyphs::Spoken, MorseSymbol::Dit) => "di",
+            (Glyphs::Spoken, MorseSymbol::Dah) => "dah",
+            (Glyphs::Spoken, MorseSymbol::LongDah) => "daah",
+            (Glyphs::Spoken, MorseSymbol::LongerDah) => "daaah",
+            (Glyphs::Binary, MorseSymbol::Dit) => "1",
+            (Glyphs::Binary, MorseSymbol::Dah) => "111",
+            (Glyphs::Binary, MorseSymbol::LongDah) => "11111",
+            (Glyphs::Binary, MorseSymbol::LongerDah) => "1111111",
+            // Only the binary glyphs mark the space itself; the joining zeros
+            // on either side make it two units long.
+            (Glyphs::Binary, MorseSymbol::Space) => "",
+            (_, MorseSymbol::Space) => " ",
+        }
+    }
+}
+
+fn parse_glyph(c: char) -> Option<MorseSymbol> {
+    match c {
+        '.' | '•' | '·' => Some(MorseSymbol::Dit),
+        '-' | '—' | '–' => Some(MorseSymbol::Dah),
+        '_' | '⸺' => Some(MorseSymbol::LongDah),
+        '=' | '⸻' => Some(MorseSymbol::LongerDah),
+        ' ' => Some(MorseSymbol::Space),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_and_parse() {
+        let elements = crate::morse::encode_str("ab c").unwrap();
+        let ascii = Notation::with_glyphs(Glyphs::Ascii);
+        assert_eq!(ascii.format(&elements), ".- -... / -.-.");
+        assert_eq!(ascii.parse(".- -... / -.-.").unwrap(), elements);
+        let unicode = Notation::default();
+        assert_eq!(unicode.parse(&unicode.format(&elements)).unwrap(), elements);
+        assert_eq!(Notation::with_glyphs(Glyphs::Spoken).format_symbols(&[MorseSymbol::Dit, MorseSymbol::Dah, MorseSymbol::Dit]), "di-dah-dit");
+        assert_eq!(Notation::with_glyphs(Glyphs::Binary).format(&elements), "10111000111010101000000011101011101");
+    }
+
+    #[test]
+    fn parse_rejects_unknown_glyphs() {
+        assert_eq!(Notation::default().parse(".-x"), Err(Error::UnknownGlyph('x')));
+    }
+}
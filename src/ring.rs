@@ -25,7 +25,7 @@ impl RingBuffer {
         self.end = (self.end + 1) % self.len();
     }
 
-    pub fn iter(&self) -> RingBufferIter {
+    pub fn iter(&self) -> RingBufferIter<'_> {
         RingBufferIter {
             buf: self,
             //cur: (self.start + 1) % self.len(),
@@ -42,6 +42,7 @@ impl std::ops::Index<usize> for RingBuffer {
     }
 }
 
+#[allow(dead_code)]
 pub struct RingBufferWindowIter<'a> {
     buf: &'a RingBuffer,
     cur: usize,
@@ -0,0 +1,125 @@
+//! The tap code, or knock code: letters laid out on a 5x5 Polybius square
+//! and sent as two groups of taps, the row and then the column. K has no
+//! square of its own and is sent as C.
+//!
+//! The tap code is a [`CodeBook`] whose codes are runs of dits, one per tap,
+//! with a space between the two groups, so the encoder, the decoder and the
+//! code table handle it like any other alphabet. Only its rhythm, a
+//! [`TapTiming`], is its own.
+
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crate::code_book::CodeBook;
+use crate::morse::{Element, MorseSymbol};
+
+/// The Polybius square, row by row.
+pub const GRID: [[char; 5]; 5] = [
+    ['a', 'b', 'c', 'd', 'e'],
+    ['f', 'g', 'h', 'i', 'j'],
+    ['l', 'm', 'n', 'o', 'p'],
+    ['q', 'r', 's', 't', 'u'],
+    ['v', 'w', 'x', 'y', 'z'],
+];
+
+/// The tap code as a code book.
+pub fn code_book() -> Arc<CodeBook> {
+    static TAP_CODE: OnceLock<Arc<CodeBook>> = OnceLock::new();
+    TAP_CODE
+        .get_or_init(|| {
+            let mut text = String::new();
+            for (row, letters) in GRID.iter().enumerate() {
+                for (column, letter) in letters.iter().enumerate() {
+                    let code = format!("{} {}", ".".repeat(row + 1), ".".repeat(column + 1));
+                    text.push_str(&format!("{} {}\n", letter, code));
+                    if *letter == 'c' {
+                        text.push_str(&format!("k {}\n", code));
+                    }
+                }
+            }
+            let book = CodeBook::parse("Tap code", &text);
+            Arc::new(book.expect("built-in code book is valid"))
+        })
+        .clone()
+}
+
+/// Whether `code_book` is the tap code.
+pub fn is_tap_code(code_book: &Arc<CodeBook>) -> bool {
+    Arc::ptr_eq(code_book, &self::code_book())
+}
+
+/// The rhythm of the tap code, counted in beats: the taps of a group are a
+/// beat apart, the two groups of a letter four beats, letters ten and words
+/// twenty. Tapping is much slower than keying morse, so it has a rhythm of
+/// its own rather than a speed in words per minute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapTiming {
+    beat: Duration,
+}
+
+/// The beats the tap rhythm can be set to.
+pub const MIN_BEAT: Duration = Duration::from_millis(50);
+pub const MAX_BEAT: Duration = Duration::from_millis(500);
+
+impl Default for TapTiming {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(150))
+    }
+}
+
+impl TapTiming {
+    pub fn new(beat: Duration) -> Self {
+        Self {
+            beat: beat.clamp(MIN_BEAT, MAX_BEAT),
+        }
+    }
+
+    pub fn beat(&self) -> Duration {
+        self.beat
+    }
+
+    /// How long a tap lasts.
+    pub fn tap(&self) -> Duration {
+        self.beat / 2
+    }
+
+    /// The pause between the taps of a group.
+    pub fn tap_gap(&self) -> Duration {
+        self.beat
+    }
+
+    /// The pause between the row and the column of a letter.
+    pub fn group_gap(&self) -> Duration {
+        self.beat * 4
+    }
+
+    pub fn letter_gap(&self) -> Duration {
+        self.beat * 10
+    }
+
+    pub fn word_gap(&self) -> Duration {
+        self.beat * 20
+    }
+
+    /// Turns a message in the tap code into the on/off periods of the key,
+    /// like [`crate::timing::Timing::timeline`] does for morse.
+    pub fn timeline(&self, elements: &[Element]) -> Vec<(bool, Duration)> {
+        let mut timeline = vec![];
+        let mut after_tap = false;
+        for element in elements {
+            match element {
+                Element::Symbol(MorseSymbol::Space) => timeline.push((false, self.group_gap())),
+                Element::Symbol(_) => {
+                    if after_tap {
+                        timeline.push((false, self.tap_gap()));
+                    }
+                    timeline.push((true, self.tap()));
+                }
+                Element::LetterGap => timeline.push((false, self.letter_gap())),
+                Element::WordGap => timeline.push((false, self.word_gap())),
+            }
+            after_tap = matches!(element, Element::Symbol(s) if *s != MorseSymbol::Space);
+        }
+        timeline
+    }
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use crate::morse::{Element, MorseSymbol};
+
+/// Keying speed, following the PARIS standard: the word "PARIS " is 50 units
+/// long, so at `wpm` words per minute a unit lasts `1200 / wpm` milliseconds.
+///
+/// With Farnsworth timing, characters are keyed at the character speed while
+/// the gaps between letters and words are stretched until the text as a whole
+/// goes at the slower effective speed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timing {
+    character_wpm: f64,
+    effective_wpm: f64,
+}
+
+impl Default for Timing {
+    fn default() -> Self {
+        Self::new(20.0)
+    }
+}
+
+impl Timing {
+    pub fn new(wpm: f64) -> Self {
+        Self::farnsworth(wpm, wpm)
+    }
+
+    /// Keys characters at `character_wpm` and spaces them out to an overall
+    /// `effective_wpm`, which is capped at the character speed.
+    pub fn farnsworth(character_wpm: f64, effective_wpm: f64) -> Self {
+        assert!(character_wpm > 0.0 && effective_wpm > 0.0, "speeds must be positive");
+        Self {
+            character_wpm,
+            effective_wpm: effective_wpm.min(character_wpm),
+        }
+    }
+
+    /// The same timing at another character speed, keeping the proportion
+    /// of any Farnsworth spacing.
+    pub fn at_character_wpm(&self, character_wpm: f64) -> Self {
+        Self::farnsworth(character_wpm, character_wpm * self.effective_wpm / self.character_wpm)
+    }
+
+    pub fn character_wpm(&self) -> f64 {
+        self.character_wpm
+    }
+
+    pub fn effective_wpm(&self) -> f64 {
+        self.effective_wpm
+    }
+
+    /// The length of a dit, and of the gap between the symbols of a character.
+    pub fn unit(&self) -> Duration {
+        Duration::from_secs_f64(1.2 / self.character_wpm)
+    }
+
+    /// How long the key is held down for `symbol`. A space inside a character
+    /// is two units of silence rather than a mark.
+    pub fn symbol(&self, symbol: MorseSymbol) -> Duration {
+        let units = match symbol {
+            MorseSymbol::Dit => 1,
+            MorseSymbol::Dah => 3,
+            MorseSymbol::LongDah => 5,
+            MorseSymbol::LongerDah => 7,
+            MorseSymbol::Space => 2,
+        };
+        self.unit() * units
+    }
+
+    pub fn letter_gap(&self) -> Duration {
+        self.farnsworth_unit().map_or(self.unit() * 3, |unit| unit.mul_f64(3.0))
+    }
+
+    pub fn word_gap(&self) -> Duration {
+        self.farnsworth_unit().map_or(self.unit() * 7, |unit| unit.mul_f64(7.0))
+    }
+
+    /// Turns a message into the on/off periods of the key.
+    pub fn timeline(&self, elements: &[Element]) -> Vec<(bool, Duration)> {
+        let mut timeline = vec![];
+        let mut push = |on: bool, duration: Duration| match timeline.last_mut() {
+            Some((last_on, last)) if *last_on == on => *last += duration,
+            _ => timeline.push((on, duration)),
+        };
+        let mut after_mark = false;
+        for element in elements {
+            match element {
+                Element::Symbol(MorseSymbol::Space) => push(false, self.symbol(MorseSymbol::Space)),
+                Element::Symbol(symbol) => {
+                    if after_mark {
+                        push(false, self.unit());
+                    }
+                    push(true, self.symbol(*symbol));
+                }
+                Element::LetterGap => push(false, self.letter_gap()),
+                Element::WordGap => push(false, self.word_gap()),
+            }
+            after_mark = matches!(element, Element::Symbol(s) if *s != MorseSymbol::Space);
+        }
+        timeline
+    }
+
+    /// The stretched unit of the Farnsworth gaps (ARRL's `ta / 19`), or
+    /// `None` when the effective speed is the character speed.
+    fn farnsworth_unit(&self) -> Option<Duration> {
+        let (c, s) = (self.character_wpm, self.effective_wpm);
+        if s >= c {
+            return None;
+        }
+        let total_delay = (60.0 * c - 37.2 * s) / (s * c);
+        Some(Duration::from_secs_f64(total_delay / 19.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn paris_is_fifty_units() {
+        let timing = Timing::new(20.0);
+        assert_eq!(timing.unit(), Duration::from_millis(60));
+        let elements = crate::morse::encode_str("paris paris").unwrap();
+        let total: Duration = timing.timeline(&elements).iter().map(|(_, d)| *d).sum();
+        assert_eq!(total + timing.word_gap(), timing.unit() * 100);
+    }
+
+    #[test]
+    fn farnsworth() {
+        let timing = Timing::farnsworth(20.0, 10.0);
+        assert_eq!(timing.unit(), Duration::from_millis(60));
+        assert!(timing.letter_gap() > timing.unit() * 3);
+        assert!(timing.word_gap() > timing.unit() * 7);
+        // "PARIS " still takes a minute divided by the effective speed.
+        let elements = crate::morse::encode_str("paris").unwrap();
+        let total: Duration = timing.timeline(&elements).iter().map(|(_, d)| *d).sum();
+        assert!(((total + timing.word_gap()).as_secs_f64() - 6.0).abs() < 0.001);
+        assert_eq!(Timing::farnsworth(20.0, 30.0), Timing::new(20.0));
+    }
+
+    #[test]
+    fn speed_change_keeps_farnsworth_spacing() {
+        let timing = Timing::farnsworth(20.0, 10.0).at_character_wpm(30.0);
+        assert_eq!(timing.character_wpm(), 30.0);
+        assert_eq!(timing.effective_wpm(), 15.0);
+        assert_eq!(Timing::new(20.0).at_character_wpm(25.0), Timing::new(25.0));
+    }
+}
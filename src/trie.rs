@@ -0,0 +1,87 @@
+use std::collections::VecDeque;
+
+use crate::morse::{MorseSymbol, Token};
+
+/// The dichotomic search tree of morse: starting from the root, every symbol
+/// moves down one branch, and a node holds the tokens whose code ends there.
+/// The tree is binary for the ITU alphabets; the long dashes and spaces of
+/// American Morse add more branches.
+#[derive(Debug, Clone)]
+pub struct Trie {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Node {
+    tokens: Vec<Token>,
+    children: [Option<usize>; MorseSymbol::ALL.len()],
+}
+
+impl Default for Trie {
+    fn default() -> Self {
+        Self {
+            nodes: vec![Node::default()],
+        }
+    }
+}
+
+impl Trie {
+    pub fn insert(&mut self, symbols: &[MorseSymbol], token: Token) {
+        let mut node = 0;
+        for symbol in symbols {
+            node = match self.nodes[node].children[*symbol as usize] {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(Node::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children[*symbol as usize] = Some(child);
+                    child
+                }
+            };
+        }
+        self.nodes[node].tokens.push(token);
+    }
+
+    /// The tokens whose code is exactly `symbols`, in insertion order.
+    pub fn get(&self, symbols: &[MorseSymbol]) -> &[Token] {
+        self.find(symbols).map_or(&[], |node| &self.nodes[node].tokens)
+    }
+
+    /// Every token whose code starts with `prefix`, shortest codes first.
+    pub fn reachable(&self, prefix: &[MorseSymbol]) -> Vec<Token> {
+        let mut tokens = vec![];
+        let mut queue: VecDeque<usize> = self.find(prefix).into_iter().collect();
+        while let Some(node) = queue.pop_front() {
+            tokens.extend(&self.nodes[node].tokens);
+            queue.extend(self.nodes[node].children.iter().flatten());
+        }
+        tokens
+    }
+
+    fn find(&self, symbols: &[MorseSymbol]) -> Option<usize> {
+        symbols
+            .iter()
+            .try_fold(0, |node, symbol| self.nodes[node].children[*symbol as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use MorseSymbol::{Dah, Dit};
+
+    #[test]
+    fn get_and_reachable() {
+        let mut trie = Trie::default();
+        trie.insert(&[Dit], Token::Char('e'));
+        trie.insert(&[Dit, Dah], Token::Char('a'));
+        trie.insert(&[Dit, Dit], Token::Char('i'));
+        trie.insert(&[Dit, Dah], Token::Char('x'));
+        assert_eq!(trie.get(&[Dit, Dah]), [Token::Char('a'), Token::Char('x')]);
+        assert_eq!(trie.get(&[Dah]), []);
+        assert_eq!(trie.get(&[Dit, Dah, Dah]), []);
+        assert_eq!(trie.reachable(&[Dit])[0], Token::Char('e'));
+        assert_eq!(trie.reachable(&[Dit]).len(), 4);
+        assert_eq!(trie.reachable(&[Dah]), []);
+    }
+}
@@ -1,22 +1,54 @@
 use std::io::{self, stdout, Stdout};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crossterm::{event::EnableMouseCapture, execute, terminal::*};
+use crossterm::{
+    event::{EnableMouseCapture, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    execute,
+    terminal::*,
+};
 use ratatui::prelude::*;
 
 /// A type alias for the terminal type used in this application
 pub type Tui = Terminal<CrosstermBackend<Stdout>>;
 
+/// Whether the keyboard enhancement flags were pushed by [`init`].
+static ENHANCED: AtomicBool = AtomicBool::new(false);
+
 /// Initialize the terminal
 pub fn init() -> io::Result<Tui> {
     execute!(stdout(), EnterAlternateScreen)?;
     execute!(stdout(), EnableMouseCapture)?;
     enable_raw_mode()?;
+    // Terminals speaking the kitty keyboard protocol can report key releases
+    // and lone modifier keys, which lets a key be held down as a straight key.
+    if supports_keyboard_enhancement().unwrap_or(false) {
+        execute!(
+            stdout(),
+            PushKeyboardEnhancementFlags(
+                KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KeyboardEnhancementFlags::REPORT_EVENT_TYPES
+                    | KeyboardEnhancementFlags::REPORT_ALTERNATE_KEYS
+                    | KeyboardEnhancementFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES
+            )
+        )?;
+        ENHANCED.store(true, Ordering::Relaxed);
+    }
     Terminal::new(CrosstermBackend::new(stdout()))
 }
 
 /// Restore the terminal to its original state
 pub fn restore() -> io::Result<()> {
+    if ENHANCED.swap(false, Ordering::Relaxed) {
+        execute!(stdout(), PopKeyboardEnhancementFlags)?;
+    }
     execute!(stdout(), LeaveAlternateScreen)?;
     disable_raw_mode()?;
     Ok(())
 }
+
+/// Whether key releases are reported, as they always are on Windows and
+/// elsewhere only by terminals that support the kitty keyboard protocol.
+/// Without them, keying is only possible with the mouse.
+pub fn reports_key_releases() -> bool {
+    cfg!(windows) || ENHANCED.load(Ordering::Relaxed)
+}